@@ -0,0 +1,130 @@
+//! The state shared by provers and verifiers, and the common operations
+//! both can perform once they hold a (possibly partially resolved) tree.
+
+use crate::batch_node::*;
+use bytes::Bytes;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Resolves a node's full contents given only its label, e.g. by reading
+/// it from a backing store. An `Rc<dyn Fn>` rather than a bare function
+/// pointer so a resolver can close over storage-specific state (a
+/// database handle, a cache, ...).
+pub type Resolver = Rc<dyn Fn(&Digest32) -> Node>;
+
+/// The tree state common to `BatchAVLProver` and `BatchAVLVerifier`: the
+/// current root, its height, the fixed key length, the (optional) fixed
+/// value length, the resolver used to fill in unresolved subtrees, and
+/// the hash function used to label nodes.
+pub struct AVLTree {
+    pub root: NodeId,
+    pub height: usize,
+    pub key_length: usize,
+    pub value_length_opt: Option<usize>,
+    pub resolver: Resolver,
+    pub hash_fn: Rc<dyn HashFn>,
+}
+
+impl AVLTree {
+    /// Builds an empty tree labeled with the default [`Blake2b256Hash`],
+    /// for backward compatibility with callers that don't care about
+    /// choosing a hash function. See [`AVLTree::with_hash_fn`] to pick
+    /// another one.
+    pub fn new(resolver: Resolver, key_length: usize, value_length_opt: Option<usize>) -> AVLTree {
+        AVLTree::with_hash_fn(resolver, key_length, value_length_opt, Rc::new(Blake2b256Hash))
+    }
+
+    /// Builds an empty tree: a root over the two sentinel leaves
+    /// (all-zero "negative infinity" and all-`0xFF` "positive infinity"
+    /// keys) that every real key is expected to fall strictly between.
+    /// Keeping them as real leaves rather than special-casing an empty
+    /// tree means lookup/insert/remove never need an "is this the first
+    /// key" branch, and range queries always have a left/right boundary
+    /// to walk from.
+    pub fn with_hash_fn(
+        resolver: Resolver,
+        key_length: usize,
+        value_length_opt: Option<usize>,
+        hash_fn: Rc<dyn HashFn>,
+    ) -> AVLTree {
+        let neg_infinity_key = Bytes::from(vec![0u8; key_length]);
+        let pos_infinity_key = Bytes::from(vec![0xFFu8; key_length]);
+        let value_len = value_length_opt.unwrap_or(0);
+
+        let neg_label = hash_fn.hash_leaf(&neg_infinity_key, &pos_infinity_key, &Bytes::from(vec![0u8; value_len]));
+        let pos_label = hash_fn.hash_leaf(&pos_infinity_key, &pos_infinity_key, &Bytes::from(vec![0u8; value_len]));
+        let root_label = hash_fn.hash_internal(0, &neg_label, &pos_label);
+
+        let mut neg_leaf = Node::new_leaf(
+            neg_infinity_key.clone(),
+            Bytes::from(vec![0u8; value_len]),
+            pos_infinity_key.clone(),
+        );
+        neg_leaf.header_mut().label = Some(neg_label);
+        let mut pos_leaf = Node::new_leaf(
+            pos_infinity_key.clone(),
+            Bytes::from(vec![0u8; value_len]),
+            pos_infinity_key.clone(),
+        );
+        pos_leaf.header_mut().label = Some(pos_label);
+
+        let mut root_node = Node::new_internal(
+            Rc::new(RefCell::new(neg_leaf)),
+            Rc::new(RefCell::new(pos_leaf)),
+            0,
+            neg_infinity_key,
+        );
+        root_node.header_mut().label = Some(root_label);
+
+        AVLTree {
+            root: Rc::new(RefCell::new(root_node)),
+            height: 1,
+            key_length,
+            value_length_opt,
+            resolver,
+            hash_fn,
+        }
+    }
+
+    /// Replace a `LabelOnly` stub in place with its resolved contents.
+    pub fn resolve(&self, node: &NodeId) {
+        let needs_resolve = matches!(*node.borrow(), Node::LabelOnly(_));
+        if needs_resolve {
+            let label = node.borrow().get_label().clone();
+            let resolved = (self.resolver)(&label);
+            *node.borrow_mut() = resolved;
+        }
+    }
+
+    /// True iff a node labeled `target` is reachable from `cur`,
+    /// resolving `LabelOnly` stubs along the way.
+    pub fn contains_label(&self, cur: &NodeId, target: &Digest32) -> bool {
+        self.resolve(cur);
+        if cur.borrow().get_label() == target {
+            return true;
+        }
+        let children = match &*cur.borrow() {
+            Node::Internal(i) => Some((i.left.clone(), i.right.clone())),
+            _ => None,
+        };
+        match children {
+            Some((l, r)) => self.contains_label(&l, target) || self.contains_label(&r, target),
+            None => false,
+        }
+    }
+}
+
+/// Operations available once a tree (prover- or verifier-side) has been
+/// built: membership checks that walk resolving stubs as needed.
+pub trait AuthenticatedTreeOps {
+    fn get_tree(&self) -> &AVLTree;
+    fn get_tree_mut(&mut self) -> &mut AVLTree;
+
+    /// True iff `node` (by label) is still reachable from the current root.
+    fn contains(&mut self, node: &NodeId) -> bool {
+        let target = node.borrow().get_label().clone();
+        let tree = self.get_tree();
+        let root = tree.root.clone();
+        tree.contains_label(&root, &target)
+    }
+}
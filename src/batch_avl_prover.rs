@@ -0,0 +1,789 @@
+//! The prover side of the authenticated tree: holds the full tree and can
+//! apply a batch of operations while recording everything a verifier
+//! would need to check the same batch against just the tree's digest.
+
+use crate::authenticated_tree_ops::*;
+use crate::batch_node::*;
+use crate::operation::*;
+use crate::range_proof::{encode_range_proof, is_sentinel_key, RangeProofEntry};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use std::fmt;
+use std::rc::Rc;
+
+pub struct BatchAVLProver {
+    tree: AVLTree,
+    collect_changed_nodes: bool,
+    changed_nodes: Vec<NodeId>,
+    deleted_nodes: Vec<NodeId>,
+}
+
+/// Controls how [`BatchAVLProver::perform_operations`] applies a batch,
+/// in particular what `Insert` does about a key that already exists.
+#[derive(Clone, Copy, Debug)]
+pub struct ApplyOptions {
+    /// Fail the whole batch if any `Insert` targets an existing key,
+    /// even if `allow_insert_or_update` is also set.
+    pub validate_insertion_does_not_override: bool,
+    /// Treat `Insert` as an upsert: update the value if the key already
+    /// exists instead of failing.
+    pub allow_insert_or_update: bool,
+    /// Stop applying the batch as soon as one operation fails, rather
+    /// than attempting the rest. Either way, only the first failing
+    /// index is reported and any failure rolls the whole batch back.
+    pub stop_on_first_error: bool,
+}
+
+impl Default for ApplyOptions {
+    fn default() -> ApplyOptions {
+        ApplyOptions {
+            validate_insertion_does_not_override: false,
+            allow_insert_or_update: false,
+            stop_on_first_error: true,
+        }
+    }
+}
+
+/// A batch failed partway through; the prover's state is exactly as it
+/// was before `perform_operations` was called.
+#[derive(Debug)]
+pub struct BatchApplyError {
+    pub failed_operation_index: usize,
+    pub source: anyhow::Error,
+}
+
+impl fmt::Display for BatchApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "operation {} failed: {}", self.failed_operation_index, self.source)
+    }
+}
+
+impl std::error::Error for BatchApplyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+impl BatchAVLProver {
+    pub fn new(tree: AVLTree, collect_changed_nodes: bool) -> BatchAVLProver {
+        BatchAVLProver {
+            tree,
+            collect_changed_nodes,
+            changed_nodes: Vec::new(),
+            deleted_nodes: Vec::new(),
+        }
+    }
+
+    /// The current root node, as the storage layer would persist it.
+    pub fn top_node(&self) -> NodeId {
+        self.tree.root.clone()
+    }
+
+    /// Nodes dropped by `remove()` since the last `generate_proof` call
+    /// (empty unless this prover was built with `collect_changed_nodes`).
+    /// Lets a caller confirm, via `AuthenticatedTreeOps::contains`, that
+    /// they're truly unreachable from the current root.
+    pub fn deleted_nodes(&self) -> &[NodeId] {
+        &self.deleted_nodes
+    }
+
+    /// `height || root label`, or `None` if the root's label hasn't been
+    /// computed yet (can't happen once the tree has been constructed,
+    /// since `AVLTree::new` labels the sentinel root eagerly).
+    pub fn digest(&self) -> Option<ADDigest> {
+        let label = self.tree.root.borrow().header().label.clone()?;
+        let mut bytes = Vec::with_capacity(1 + label.len());
+        bytes.push(self.tree.height as u8);
+        bytes.extend_from_slice(&label);
+        Some(Bytes::from(bytes))
+    }
+
+    pub fn perform_one_operation(&mut self, operation: &Operation) -> Result<Option<ADValue>> {
+        match operation {
+            Operation::Insert(kv) => self.insert(&kv.key, &kv.value, false),
+            Operation::Update(kv) => self.update(&kv.key, &kv.value),
+            Operation::InsertOrUpdate(kv) => self.insert(&kv.key, &kv.value, true),
+            Operation::Remove(key) => self.remove(key),
+            Operation::Lookup(key) => Ok(self.lookup(key)),
+        }
+    }
+
+    /// Applies `ops` as a single batch under `options`. If any operation
+    /// fails, the whole batch is rolled back — the prover ends up exactly
+    /// as it was before this call — and the error identifies the first
+    /// (or, with `stop_on_first_error: false`, the first of possibly
+    /// several) failing operation by index.
+    pub fn perform_operations(
+        &mut self,
+        ops: &[Operation],
+        options: &ApplyOptions,
+    ) -> std::result::Result<Vec<Option<ADValue>>, BatchApplyError> {
+        let snapshot_root = deep_clone(&self.tree.root);
+        let snapshot_height = self.tree.height;
+        let snapshot_changed_nodes_len = self.changed_nodes.len();
+        let snapshot_deleted_nodes_len = self.deleted_nodes.len();
+
+        let mut results = Vec::with_capacity(ops.len());
+        let mut first_failure = None;
+
+        for (index, op) in ops.iter().enumerate() {
+            match self.apply_with_options(op, options) {
+                Ok(value) => results.push(value),
+                Err(source) => {
+                    if first_failure.is_none() {
+                        first_failure = Some((index, source));
+                    }
+                    if options.stop_on_first_error {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some((failed_operation_index, source)) = first_failure {
+            self.tree.root = snapshot_root;
+            self.tree.height = snapshot_height;
+            // Operations before the failing one may have already pushed
+            // entries into these via relabel_path/remove; since the tree
+            // itself is rolled back, those entries must not survive into
+            // the next generate_proof() call either.
+            self.changed_nodes.truncate(snapshot_changed_nodes_len);
+            self.deleted_nodes.truncate(snapshot_deleted_nodes_len);
+            return Err(BatchApplyError { failed_operation_index, source });
+        }
+
+        Ok(results)
+    }
+
+    fn apply_with_options(&mut self, op: &Operation, options: &ApplyOptions) -> Result<Option<ADValue>> {
+        match op {
+            Operation::Insert(kv) => {
+                let allow_update = options.allow_insert_or_update && !options.validate_insertion_does_not_override;
+                self.insert(&kv.key, &kv.value, allow_update)
+            }
+            other => self.perform_one_operation(other),
+        }
+    }
+
+    /// Serializes every node touched since the last call and clears the
+    /// change set, so the next proof only covers what's new.
+    pub fn generate_proof(&mut self) -> SerializedAdProof {
+        let mut buf = Vec::new();
+        for node in &self.changed_nodes {
+            Self::append_node(&mut buf, node, 0x01);
+        }
+        for node in &self.deleted_nodes {
+            Self::append_node(&mut buf, node, 0x00);
+        }
+        self.changed_nodes.clear();
+        self.deleted_nodes.clear();
+        Bytes::from(buf)
+    }
+
+    pub fn contains_key(&mut self, key: &ADKey) -> bool {
+        self.lookup(key).is_some()
+    }
+
+    fn append_node(buf: &mut Vec<u8>, node: &NodeId, tag: u8) {
+        buf.push(tag);
+        buf.extend_from_slice(node.borrow().get_label());
+    }
+
+    /// Walks from the root to the leaf that `key` would live at (its
+    /// current holder if present, otherwise its would-be predecessor),
+    /// resolving `LabelOnly` stubs along the way. The returned path
+    /// always starts at the root and ends at a leaf.
+    ///
+    /// A routing-key descent alone can overshoot `key`'s predecessor: if
+    /// it goes right at some node because `key > routing_key`, nothing
+    /// guarantees the right subtree actually contains anything `<= key`
+    /// (e.g. the two-sentinel-leaf tree, where the only right turn from
+    /// the root lands straight on the positive-infinity leaf). When that
+    /// happens we back up to the nearest such right turn and take its
+    /// left sibling's rightmost leaf instead, which is guaranteed to be
+    /// `<= key` since it's exactly that ancestor's `routing_key`.
+    fn locate_leaf(&self, key: &ADKey) -> Vec<NodeId> {
+        let path = self.naive_descent(key);
+        let overshot = matches!(&*path.last().unwrap().borrow(), Node::Leaf(l) if l.key > *key);
+        if !overshot {
+            return path;
+        }
+        self.rightmost_of_nearest_right_turn(&path).unwrap_or(path)
+    }
+
+    /// The plain routing-key descent `locate_leaf` builds on, with no
+    /// overshoot correction.
+    fn naive_descent(&self, key: &ADKey) -> Vec<NodeId> {
+        let mut path = Vec::new();
+        let mut cur = self.tree.root.clone();
+        loop {
+            self.tree.resolve(&cur);
+            path.push(cur.clone());
+            let next = match &*cur.borrow() {
+                Node::Internal(i) => {
+                    if *key <= i.routing_key {
+                        Some(i.left.clone())
+                    } else {
+                        Some(i.right.clone())
+                    }
+                }
+                Node::Leaf(_) => None,
+                Node::LabelOnly(_) => unreachable!("resolved above"),
+            };
+            match next {
+                Some(n) => cur = n,
+                None => return path,
+            }
+        }
+    }
+
+    /// Walks `path` up to the nearest ancestor reached by going right,
+    /// then descends that ancestor's left sibling to its rightmost leaf.
+    /// Returns `None` if `path` never went right (i.e. it started at the
+    /// global minimum, which has no predecessor).
+    fn rightmost_of_nearest_right_turn(&self, path: &[NodeId]) -> Option<Vec<NodeId>> {
+        for i in (1..path.len()).rev() {
+            let parent = &path[i - 1];
+            let went_right = matches!(&*parent.borrow(), Node::Internal(p) if Rc::ptr_eq(&p.right, &path[i]));
+            if !went_right {
+                continue;
+            }
+            let left_sibling = match &*parent.borrow() {
+                Node::Internal(p) => p.left.clone(),
+                _ => unreachable!(),
+            };
+            let mut result = path[..i].to_vec();
+            let mut cur = left_sibling;
+            loop {
+                self.tree.resolve(&cur);
+                result.push(cur.clone());
+                let next = match &*cur.borrow() {
+                    Node::Internal(p) => Some(p.right.clone()),
+                    Node::Leaf(_) => None,
+                    Node::LabelOnly(_) => unreachable!("resolved above"),
+                };
+                match next {
+                    Some(n) => cur = n,
+                    None => return Some(result),
+                }
+            }
+        }
+        None
+    }
+
+    /// Recomputes labels bottom-up along `path`, e.g. after mutating its
+    /// last node. Safe to call on overlapping paths since it's a pure
+    /// function of each node's current children/contents.
+    ///
+    /// Also marks every relabeled node `is_new`: its serialized form
+    /// (which embeds its children's labels) just changed, so a storage
+    /// layer that skips already-persisted subtrees (see
+    /// `versioned_avl_storage_rocksdb::persist_new_nodes`) needs to write
+    /// it again even though it existed before this operation.
+    fn relabel_path(&mut self, path: &[NodeId]) {
+        for node in path.iter().rev() {
+            self.relabel_node(node);
+        }
+    }
+
+    /// Recomputes and stores a single node's label from its current
+    /// children/contents, marking it `is_new` and recording it in
+    /// `changed_nodes`. The shared body behind `relabel_path`, and also
+    /// used by `rebalance_node` to keep an unrotated node's label in sync
+    /// with children that grew beneath it — otherwise a shallower rotation
+    /// could bake that node's stale label into a freshly-built parent via
+    /// `finish_rotated_node` before `relabel_path` ever got to refresh it.
+    fn relabel_node(&mut self, node: &NodeId) {
+        let new_label = {
+            let borrowed = node.borrow();
+            match &*borrowed {
+                Node::Leaf(l) => self.tree.hash_fn.hash_leaf(&l.key, &l.next_leaf_key, &l.value),
+                Node::Internal(i) => {
+                    self.tree
+                        .hash_fn
+                        .hash_internal(i.balance, i.left.borrow().get_label(), i.right.borrow().get_label())
+                }
+                Node::LabelOnly(_) => return,
+            }
+        };
+        let mut borrowed = node.borrow_mut();
+        let header = borrowed.header_mut();
+        header.label = Some(new_label);
+        header.is_new = true;
+        drop(borrowed);
+        if self.collect_changed_nodes {
+            self.changed_nodes.push(node.clone());
+        }
+    }
+
+    /// Balance factor (right height minus left height) of the subtree
+    /// rooted at `node`, computed directly from its children's real
+    /// heights rather than from a maintained counter — the
+    /// same approach `compute_height` already takes for `tree.height`.
+    fn subtree_balance(node: &NodeId) -> i64 {
+        match &*node.borrow() {
+            Node::Internal(i) => Self::compute_height(&i.right) as i64 - Self::compute_height(&i.left) as i64,
+            _ => 0,
+        }
+    }
+
+    fn set_child(node: &NodeId, left: bool, child: NodeId) {
+        if let Node::Internal(i) = &mut *node.borrow_mut() {
+            if left {
+                i.left = child;
+            } else {
+                i.right = child;
+            }
+        }
+    }
+
+    /// Finishes building a node produced by a rotation: fills in its
+    /// balance factor and label from its (already-labeled) children, and
+    /// marks it new so storage layers persist it. Rotations can
+    /// introduce an internal node (e.g. the inner pivot of a double
+    /// rotation) that never appears as its own entry in a `path` passed
+    /// to `relabel_path`, so it has to be labeled here instead.
+    fn finish_rotated_node(&mut self, node: &NodeId) {
+        let (left, right) = match &*node.borrow() {
+            Node::Internal(i) => (i.left.clone(), i.right.clone()),
+            _ => unreachable!("only internal nodes come out of a rotation"),
+        };
+        let balance = (Self::compute_height(&right) as i64 - Self::compute_height(&left) as i64) as i8;
+        let label = self.tree.hash_fn.hash_internal(balance, left.borrow().get_label(), right.borrow().get_label());
+        let mut borrowed = node.borrow_mut();
+        if let Node::Internal(i) = &mut *borrowed {
+            i.balance = balance;
+            i.header.label = Some(label);
+            i.header.is_new = true;
+        }
+        drop(borrowed);
+        if self.collect_changed_nodes {
+            self.changed_nodes.push(node.clone());
+        }
+    }
+
+    /// Right rotation: `y = x.left` becomes the new subtree root, with
+    /// `x` demoted to `y`'s right child. Routing keys are untouched —
+    /// they describe which keys live in a node's left subtree, which a
+    /// rotation doesn't change, only the shape around them does.
+    fn rotate_right(&mut self, x: &NodeId) -> NodeId {
+        let (y, x_right, x_routing_key) = match &*x.borrow() {
+            Node::Internal(i) => (i.left.clone(), i.right.clone(), i.routing_key.clone()),
+            _ => unreachable!("rotation pivot must be internal"),
+        };
+        let (y_left, y_right, y_routing_key) = match &*y.borrow() {
+            Node::Internal(i) => (i.left.clone(), i.right.clone(), i.routing_key.clone()),
+            _ => unreachable!("the heavy child of an unbalanced node is always internal"),
+        };
+        let new_x = Rc::new(std::cell::RefCell::new(Node::new_internal(y_right, x_right, 0, x_routing_key)));
+        self.finish_rotated_node(&new_x);
+        let new_y = Rc::new(std::cell::RefCell::new(Node::new_internal(y_left, new_x, 0, y_routing_key)));
+        self.finish_rotated_node(&new_y);
+        new_y
+    }
+
+    /// Left rotation, the mirror image of `rotate_right`.
+    fn rotate_left(&mut self, x: &NodeId) -> NodeId {
+        let (x_left, y, x_routing_key) = match &*x.borrow() {
+            Node::Internal(i) => (i.left.clone(), i.right.clone(), i.routing_key.clone()),
+            _ => unreachable!("rotation pivot must be internal"),
+        };
+        let (y_left, y_right, y_routing_key) = match &*y.borrow() {
+            Node::Internal(i) => (i.left.clone(), i.right.clone(), i.routing_key.clone()),
+            _ => unreachable!("the heavy child of an unbalanced node is always internal"),
+        };
+        let new_x = Rc::new(std::cell::RefCell::new(Node::new_internal(x_left, y_left, 0, x_routing_key)));
+        self.finish_rotated_node(&new_x);
+        let new_y = Rc::new(std::cell::RefCell::new(Node::new_internal(new_x, y_right, 0, y_routing_key)));
+        self.finish_rotated_node(&new_y);
+        new_y
+    }
+
+    /// Restores the AVL invariant at `node` if its children's heights now
+    /// differ by more than one, via the usual single/double rotation,
+    /// returning the (possibly different) subtree root. Otherwise just
+    /// refreshes `node`'s own balance factor and label and hands it back
+    /// unchanged.
+    fn rebalance_node(&mut self, node: &NodeId) -> NodeId {
+        let (left, right) = match &*node.borrow() {
+            Node::Internal(i) => (i.left.clone(), i.right.clone()),
+            _ => return node.clone(),
+        };
+        let balance = Self::compute_height(&right) as i64 - Self::compute_height(&left) as i64;
+
+        if balance.abs() <= 1 {
+            if let Node::Internal(i) = &mut *node.borrow_mut() {
+                i.balance = balance as i8;
+            }
+            // A child beneath `node` may have grown without `node` itself
+            // rotating, leaving its cached label stale. Refresh it now,
+            // rather than waiting on the caller's later `relabel_path`
+            // pass — a shallower rotation can read this label via
+            // `finish_rotated_node` before that pass ever runs.
+            self.relabel_node(node);
+            return node.clone();
+        }
+
+        if balance == 2 {
+            if Self::subtree_balance(&right) < 0 {
+                let new_right = self.rotate_right(&right);
+                Self::set_child(node, false, new_right);
+            }
+            self.rotate_left(node)
+        } else {
+            if Self::subtree_balance(&left) > 0 {
+                let new_left = self.rotate_left(&left);
+                Self::set_child(node, true, new_left);
+            }
+            self.rotate_right(node)
+        }
+    }
+
+    /// Rebalances every internal node on `path` (root-to-node order),
+    /// from the bottom up, after a structural change beneath
+    /// `path.last()` may have thrown a child height out of AVL balance.
+    /// Mutates `path` in place so each entry reflects the (possibly
+    /// rotated) node now at that position, and repairs its parent's
+    /// child pointer — or the tree root — to match. Every internal node
+    /// this touches already carries a fresh, correct label by the time it
+    /// returns, so callers need no separate `relabel_path` over `path`.
+    /// A leaf at `path.last()` is skipped and must already be labeled.
+    fn rebalance_path(&mut self, path: &mut [NodeId]) {
+        for i in (0..path.len()).rev() {
+            let node = path[i].clone();
+            if !matches!(&*node.borrow(), Node::Internal(_)) {
+                continue;
+            }
+            let rebalanced = self.rebalance_node(&node);
+            if Rc::ptr_eq(&rebalanced, &node) {
+                continue;
+            }
+            path[i] = rebalanced.clone();
+            if i == 0 {
+                self.tree.root = rebalanced;
+            } else {
+                let parent = &path[i - 1];
+                let mut borrowed = parent.borrow_mut();
+                match &mut *borrowed {
+                    Node::Internal(p) if Rc::ptr_eq(&p.left, &node) => p.left = rebalanced,
+                    Node::Internal(p) => p.right = rebalanced,
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    fn lookup(&self, key: &ADKey) -> Option<ADValue> {
+        let path = self.locate_leaf(key);
+        let borrowed = path.last().unwrap().borrow();
+        match &*borrowed {
+            Node::Leaf(l) if l.key == *key => Some(l.value.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, key: &ADKey, value: &ADValue, allow_update: bool) -> Result<Option<ADValue>> {
+        let path = self.locate_leaf(key);
+        let leaf = path.last().unwrap().clone();
+
+        let already_present = matches!(&*leaf.borrow(), Node::Leaf(l) if l.key == *key);
+        if already_present {
+            if !allow_update {
+                return Err(anyhow!("key already exists"));
+            }
+            let old_value = {
+                let mut borrowed = leaf.borrow_mut();
+                match &mut *borrowed {
+                    Node::Leaf(l) => std::mem::replace(&mut l.value, value.clone()),
+                    _ => unreachable!(),
+                }
+            };
+            self.relabel_path(&path);
+            return Ok(Some(old_value));
+        }
+
+        // `leaf` is the predecessor of `key`: leaf.key < key < leaf.next_leaf_key.
+        let old_next_leaf_key = match &*leaf.borrow() {
+            Node::Leaf(l) => l.next_leaf_key.clone(),
+            _ => unreachable!(),
+        };
+        let new_leaf = Rc::new(std::cell::RefCell::new(Node::new_leaf(
+            key.clone(),
+            value.clone(),
+            old_next_leaf_key,
+        )));
+        {
+            let mut borrowed = leaf.borrow_mut();
+            match &mut *borrowed {
+                Node::Leaf(l) => l.next_leaf_key = key.clone(),
+                _ => unreachable!(),
+            }
+        }
+        let routing_key = match &*leaf.borrow() {
+            Node::Leaf(l) => l.key.clone(),
+            _ => unreachable!(),
+        };
+        let new_internal = Rc::new(std::cell::RefCell::new(Node::new_internal(
+            leaf.clone(),
+            new_leaf.clone(),
+            0,
+            routing_key.clone(),
+        )));
+
+        // Any ancestor whose routing_key equals `leaf`'s key was caching
+        // `leaf` as the max of its left subtree; `key` (strictly larger,
+        // since `leaf` is `key`'s predecessor) is now that max instead.
+        for ancestor in &path[..path.len() - 1] {
+            if let Node::Internal(i) = &mut *ancestor.borrow_mut() {
+                if i.routing_key == routing_key {
+                    i.routing_key = key.clone();
+                }
+            }
+        }
+
+        let mut ancestor_path = path[..path.len() - 1].to_vec();
+        ancestor_path.push(new_internal.clone());
+        if ancestor_path.len() == 1 {
+            self.tree.root = new_internal;
+        } else {
+            let parent = &ancestor_path[ancestor_path.len() - 2];
+            let mut borrowed = parent.borrow_mut();
+            match &mut *borrowed {
+                Node::Internal(i) if Rc::ptr_eq(&i.left, &leaf) => i.left = new_internal,
+                Node::Internal(i) => i.right = new_internal,
+                _ => unreachable!(),
+            }
+        }
+
+        // Label the newly split leaf pair and the internal node joining
+        // them before rebalancing: a rotation above them may need to
+        // fold their labels into its own hash right away.
+        let new_internal = ancestor_path.last().unwrap().clone();
+        self.relabel_path(&[new_internal, new_leaf, leaf]);
+
+        // The freshly split leaf pair replaced a single leaf one level
+        // deeper than before, so ancestors above it may now be
+        // unbalanced; fix the tree shape. Every node on `ancestor_path`
+        // already comes out of `rebalance_path` correctly labeled — no
+        // separate `relabel_path` pass needed.
+        self.rebalance_path(&mut ancestor_path);
+        self.tree.height = Self::compute_height(&self.tree.root);
+        Ok(None)
+    }
+
+    fn update(&mut self, key: &ADKey, value: &ADValue) -> Result<Option<ADValue>> {
+        let path = self.locate_leaf(key);
+        let leaf = path.last().unwrap().clone();
+        let old_value = {
+            let mut borrowed = leaf.borrow_mut();
+            match &mut *borrowed {
+                Node::Leaf(l) if l.key == *key => Some(std::mem::replace(&mut l.value, value.clone())),
+                _ => None,
+            }
+        };
+        if old_value.is_none() {
+            return Err(anyhow!("key not found"));
+        }
+        self.relabel_path(&path);
+        Ok(old_value)
+    }
+
+    fn remove(&mut self, key: &ADKey) -> Result<Option<ADValue>> {
+        let mut path = self.locate_leaf(key);
+        let leaf = path.pop().unwrap();
+        let found = matches!(&*leaf.borrow(), Node::Leaf(l) if l.key == *key);
+        if !found {
+            return Err(anyhow!("key not found"));
+        }
+        let parent = path.pop().expect("leaf always has a parent (sentinels guard the root)");
+
+        let (removed_value, removed_next_key) = match &*leaf.borrow() {
+            Node::Leaf(l) => (l.value.clone(), l.next_leaf_key.clone()),
+            _ => unreachable!(),
+        };
+        let sibling = {
+            let borrowed = parent.borrow();
+            match &*borrowed {
+                Node::Internal(i) if Rc::ptr_eq(&i.left, &leaf) => i.right.clone(),
+                Node::Internal(i) => i.left.clone(),
+                _ => unreachable!(),
+            }
+        };
+
+        if self.collect_changed_nodes {
+            self.deleted_nodes.push(leaf.clone());
+            self.deleted_nodes.push(parent.clone());
+        }
+
+        if let Some(grandparent) = path.last() {
+            let mut borrowed = grandparent.borrow_mut();
+            match &mut *borrowed {
+                Node::Internal(i) if Rc::ptr_eq(&i.left, &parent) => i.left = sibling,
+                Node::Internal(i) => i.right = sibling,
+                _ => unreachable!(),
+            }
+        } else {
+            self.tree.root = sibling;
+        }
+
+        // `key` is now absent, so locating it again naturally lands on
+        // its predecessor leaf, whose `next_leaf_key` must skip over it.
+        let predecessor_path = self.locate_leaf(key);
+        let predecessor_key = match &*predecessor_path.last().unwrap().borrow() {
+            Node::Leaf(l) => l.key.clone(),
+            _ => unreachable!(),
+        };
+        // Any remaining ancestor whose routing_key equals the removed
+        // key was caching `leaf` as the max of its left subtree; that
+        // max is now the removed key's predecessor instead.
+        for ancestor in &path {
+            if let Node::Internal(i) = &mut *ancestor.borrow_mut() {
+                if i.routing_key == *key {
+                    i.routing_key = predecessor_key.clone();
+                }
+            }
+        }
+
+        // `sibling` took `parent`'s place one level higher up than it
+        // used to sit, so everything above it may now be unbalanced.
+        // `rebalance_path` already leaves every node on `path` correctly
+        // labeled.
+        self.rebalance_path(&mut path);
+
+        // Rotations may have replaced nodes on the path found above, so
+        // walk to the predecessor again through the rebalanced tree.
+        let predecessor_path = self.locate_leaf(key);
+        {
+            let predecessor = predecessor_path.last().unwrap();
+            match &mut *predecessor.borrow_mut() {
+                Node::Leaf(l) => l.next_leaf_key = removed_next_key,
+                _ => unreachable!(),
+            }
+        }
+        self.relabel_path(&predecessor_path);
+        self.tree.height = Self::compute_height(&self.tree.root);
+        Ok(Some(removed_value))
+    }
+
+    fn compute_height(node: &NodeId) -> usize {
+        match &*node.borrow() {
+            Node::Internal(i) => 1 + Self::compute_height(&i.left).max(Self::compute_height(&i.right)),
+            _ => 0,
+        }
+    }
+
+    /// Authenticates every key/value pair in `[lo, hi]` with a single
+    /// proof, by walking the leaf-level linked list (`next_leaf_key`)
+    /// from `lo`'s predecessor up to the first leaf past `hi`. Pass the
+    /// returned proof and the digest from *before* this call to
+    /// [`crate::range_proof::verify_range_proof`].
+    pub fn generate_range_proof(&self, lo: &ADKey, hi: &ADKey) -> Result<(Vec<KeyValue>, SerializedAdProof)> {
+        if lo > hi {
+            return Err(anyhow!("empty range: lo > hi"));
+        }
+
+        let boundary_path = self.predecessor_path(lo);
+        let mut proof_entries = vec![self.proof_entry(&boundary_path)];
+        let mut result = Vec::new();
+
+        let mut next_key = match &*boundary_path.last().unwrap().borrow() {
+            Node::Leaf(l) => l.next_leaf_key.clone(),
+            _ => unreachable!(),
+        };
+
+        loop {
+            let path = self.locate_leaf(&next_key);
+            let (key, leaf_next_key, value) = match &*path.last().unwrap().borrow() {
+                Node::Leaf(l) => (l.key.clone(), l.next_leaf_key.clone(), l.value.clone()),
+                _ => unreachable!(),
+            };
+            proof_entries.push(self.proof_entry(&path));
+            if key <= *hi && !is_sentinel_key(&key) {
+                result.push(KeyValue { key: key.clone(), value });
+            }
+            if key > *hi || key == leaf_next_key {
+                // Either we've passed `hi`, or `key == leaf_next_key`
+                // means we hit the positive-infinity sentinel (which
+                // points to itself) and ran out of tree either way.
+                break;
+            }
+            next_key = leaf_next_key;
+        }
+
+        Ok((result, encode_range_proof(&proof_entries)))
+    }
+
+    /// Authenticates a batch of lookups with a single proof: one
+    /// authentication-path entry per key, in the order given. Lookups
+    /// never mutate the tree, so every entry is checked against the same
+    /// digest — the one from *before* this call. Pass the returned proof
+    /// alongside that digest to
+    /// [`crate::batch_avl_verifier::BatchAVLVerifier::new`].
+    pub fn generate_lookup_proof(&self, keys: &[ADKey]) -> SerializedAdProof {
+        let entries: Vec<RangeProofEntry> = keys.iter().map(|key| self.proof_entry(&self.locate_leaf(key))).collect();
+        encode_range_proof(&entries)
+    }
+
+    /// The root-to-leaf path to the boundary leaf for a range starting at
+    /// `lo`: the leaf whose key is the largest strictly less than `lo`,
+    /// or the negative-infinity sentinel if nothing is. Lets a verifier
+    /// confirm no qualifying key before `lo` was skipped.
+    fn predecessor_path(&self, lo: &ADKey) -> Vec<NodeId> {
+        let path = self.locate_leaf(lo);
+        let exact_match = matches!(&*path.last().unwrap().borrow(), Node::Leaf(l) if l.key == *lo);
+        if !exact_match {
+            return path;
+        }
+
+        // `lo` is itself a leaf: its predecessor is the rightmost leaf of
+        // the left sibling at the nearest ancestor where we descended
+        // right to reach it. If we never went right, `lo` is the global
+        // minimum (the negative-infinity sentinel) and has none.
+        self.rightmost_of_nearest_right_turn(&path).unwrap_or(path)
+    }
+
+    /// Bundles the leaf at the end of `path` with its root-to-leaf
+    /// authentication path into a [`RangeProofEntry`].
+    fn proof_entry(&self, path: &[NodeId]) -> RangeProofEntry {
+        let (key, next_leaf_key, value) = match &*path.last().unwrap().borrow() {
+            Node::Leaf(l) => (l.key.clone(), l.next_leaf_key.clone(), l.value.clone()),
+            _ => unreachable!(),
+        };
+        RangeProofEntry {
+            key,
+            next_leaf_key,
+            value,
+            path: self.authentication_path(path),
+        }
+    }
+
+    /// For each internal node on `path`, whether the next step went left,
+    /// its balance, and its sibling's label — everything needed to fold a
+    /// leaf's label back up to an implied root label.
+    fn authentication_path(&self, path: &[NodeId]) -> Vec<(bool, i8, Digest32)> {
+        path.windows(2)
+            .map(|pair| match &*pair[0].borrow() {
+                Node::Internal(i) => {
+                    let went_left = Rc::ptr_eq(&i.left, &pair[1]);
+                    let sibling_label = if went_left {
+                        i.right.borrow().get_label().clone()
+                    } else {
+                        i.left.borrow().get_label().clone()
+                    };
+                    (went_left, i.balance, sibling_label)
+                }
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+}
+
+impl AuthenticatedTreeOps for BatchAVLProver {
+    fn get_tree(&self) -> &AVLTree {
+        &self.tree
+    }
+
+    fn get_tree_mut(&mut self) -> &mut AVLTree {
+        &mut self.tree
+    }
+}
@@ -0,0 +1,123 @@
+//! The verifier side: replays a batch of `Lookup` operations against a
+//! proof produced by [`BatchAVLProver::generate_lookup_proof`], one
+//! authentication-path entry per key, checking each against the tree's
+//! digest without ever holding more of the tree than that path.
+//!
+//! Mutating operations (`Insert`/`Update`/`Remove`) aren't supported:
+//! the prover doesn't emit a proof format rich enough to let a verifier
+//! replay their rebalancing, so `perform_one_operation` rejects them.
+
+use crate::batch_node::*;
+use crate::operation::*;
+use crate::range_proof::{decode_range_proof, RangeProofEntry};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+pub struct BatchAVLVerifier {
+    root_label: Digest32,
+    height: usize,
+    entries: VecDeque<RangeProofEntry>,
+    max_num_operations: Option<usize>,
+    max_deletes: Option<usize>,
+    operations_performed: usize,
+    deletes_performed: usize,
+}
+
+impl BatchAVLVerifier {
+    /// Decodes `proof` into one authentication-path entry per lookup and
+    /// checks every one of them against `initial_digest` up front, so a
+    /// proof that doesn't match the digest is rejected before any
+    /// operation is replayed.
+    pub fn new(
+        initial_digest: &ADDigest,
+        proof: &SerializedAdProof,
+        hash_fn: Rc<dyn HashFn>,
+        max_num_operations: Option<usize>,
+        max_deletes: Option<usize>,
+    ) -> Result<BatchAVLVerifier> {
+        if initial_digest.len() != 1 + hash_fn.output_len() {
+            return Err(anyhow!(
+                "unexpected digest length: expected {}, got {}",
+                1 + hash_fn.output_len(),
+                initial_digest.len()
+            ));
+        }
+        let height = initial_digest[0] as usize;
+        let root_label = Bytes::copy_from_slice(&initial_digest[1..]);
+
+        let entries = decode_range_proof(proof)?;
+        for entry in &entries {
+            if entry.implied_root_label(hash_fn.as_ref()) != root_label {
+                return Err(anyhow!("a lookup entry in the proof does not authenticate against the digest"));
+            }
+        }
+
+        Ok(BatchAVLVerifier {
+            root_label,
+            height,
+            entries: entries.into(),
+            max_num_operations,
+            max_deletes,
+            operations_performed: 0,
+            deletes_performed: 0,
+        })
+    }
+
+    /// `height || root label`, mirroring `BatchAVLProver::digest`. Lookups
+    /// never change it, so it stays constant across the whole batch.
+    pub fn digest(&self) -> ADDigest {
+        let mut bytes = Vec::with_capacity(1 + self.root_label.len());
+        bytes.push(self.height as u8);
+        bytes.extend_from_slice(&self.root_label);
+        Bytes::from(bytes)
+    }
+
+    pub fn perform_one_operation(&mut self, operation: &Operation) -> Result<Option<ADValue>> {
+        if let Some(max) = self.max_num_operations {
+            if self.operations_performed >= max {
+                return Err(anyhow!("maximum number of operations exceeded"));
+            }
+        }
+        if matches!(operation, Operation::Remove(_)) {
+            if let Some(max) = self.max_deletes {
+                if self.deletes_performed >= max {
+                    return Err(anyhow!("maximum number of deletes exceeded"));
+                }
+                self.deletes_performed += 1;
+            }
+        }
+        self.operations_performed += 1;
+
+        match operation {
+            Operation::Lookup(key) => self.lookup(key),
+            _ => Err(anyhow!(
+                "this verifier only replays Lookup; mutating operations require a richer proof \
+                 format than generate_lookup_proof produces"
+            )),
+        }
+    }
+
+    /// Consumes the next authentication-path entry (lookups are replayed
+    /// in the same order `generate_lookup_proof` was given their keys)
+    /// and answers `key` from it: present if the entry's own key matches,
+    /// absent if the entry is `key`'s immediate predecessor in sorted
+    /// order (per the `next_leaf_key` linked list the tree maintains).
+    /// Anything else means the entry doesn't authenticate `key` at all —
+    /// the proof and the operation being replayed don't line up.
+    fn lookup(&mut self, key: &ADKey) -> Result<Option<ADValue>> {
+        let entry = self
+            .entries
+            .pop_front()
+            .ok_or_else(|| anyhow!("proof exhausted: no authentication path left for this lookup"))?;
+
+        if entry.key == *key {
+            return Ok(Some(entry.value));
+        }
+        if entry.key < *key && *key < entry.next_leaf_key {
+            return Ok(None);
+        }
+        Err(anyhow!("proof entry does not authenticate this key"))
+    }
+}
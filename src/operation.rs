@@ -0,0 +1,32 @@
+//! Operations that can be batched into a single prove/verify round.
+
+use crate::batch_node::{ADKey, ADValue};
+
+/// A key/value pair, the payload of `Insert`/`Update`/`InsertOrUpdate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyValue {
+    pub key: ADKey,
+    pub value: ADValue,
+}
+
+/// A single authenticated-tree mutation or read.
+#[derive(Clone, Debug)]
+pub enum Operation {
+    Insert(KeyValue),
+    Update(KeyValue),
+    InsertOrUpdate(KeyValue),
+    Remove(ADKey),
+    Lookup(ADKey),
+}
+
+impl Operation {
+    pub fn key(&self) -> &ADKey {
+        match self {
+            Operation::Insert(kv) => &kv.key,
+            Operation::Update(kv) => &kv.key,
+            Operation::InsertOrUpdate(kv) => &kv.key,
+            Operation::Remove(k) => k,
+            Operation::Lookup(k) => k,
+        }
+    }
+}
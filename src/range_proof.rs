@@ -0,0 +1,194 @@
+//! Authenticated range queries: a single proof that authenticates every
+//! key/value pair in `[lo, hi]` against a tree's digest, together with
+//! *completeness* — a guarantee that no qualifying pair was omitted.
+//!
+//! The proof is a sequence of leaves, starting with the boundary leaf
+//! whose key is the largest strictly less than `lo` (or the
+//! negative-infinity sentinel, if nothing is), then every leaf from
+//! there on via `next_leaf_key` up to and including the first leaf whose
+//! key exceeds `hi`. Each leaf carries its own Merkle authentication path
+//! to the root, so the verifier never needs more than the digest to
+//! check it.
+
+use crate::batch_node::*;
+use crate::operation::KeyValue;
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use std::convert::TryInto;
+
+/// A leaf visited while answering a range query, plus the root-to-leaf
+/// authentication path: `(went_left, balance, sibling_label)` for each
+/// internal node on the way down.
+pub(crate) struct RangeProofEntry {
+    pub key: ADKey,
+    pub next_leaf_key: ADKey,
+    pub value: ADValue,
+    pub path: Vec<(bool, i8, Digest32)>,
+}
+
+impl RangeProofEntry {
+    /// Recomputes this leaf's label and folds it up the authentication
+    /// path to get the label it implies for the root.
+    pub fn implied_root_label(&self, hash_fn: &dyn HashFn) -> Digest32 {
+        let mut label = hash_fn.hash_leaf(&self.key, &self.next_leaf_key, &self.value);
+        for (went_left, balance, sibling_label) in self.path.iter().rev() {
+            label = if *went_left {
+                hash_fn.hash_internal(*balance, &label, sibling_label)
+            } else {
+                hash_fn.hash_internal(*balance, sibling_label, &label)
+            };
+        }
+        label
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_bytes(buf, &self.key);
+        write_bytes(buf, &self.next_leaf_key);
+        write_bytes(buf, &self.value);
+        buf.extend_from_slice(&(self.path.len() as u32).to_be_bytes());
+        for (went_left, balance, sibling_label) in &self.path {
+            buf.push(*went_left as u8);
+            buf.push(*balance as u8);
+            write_bytes(buf, sibling_label);
+        }
+    }
+
+    fn decode(buf: &[u8], pos: &mut usize) -> Result<RangeProofEntry> {
+        let key = read_bytes(buf, pos)?;
+        let next_leaf_key = read_bytes(buf, pos)?;
+        let value = read_bytes(buf, pos)?;
+        let path_len = read_u32(buf, pos)? as usize;
+        let mut path = Vec::with_capacity(path_len);
+        for _ in 0..path_len {
+            let went_left = read_byte(buf, pos)? != 0;
+            let balance = read_byte(buf, pos)? as i8;
+            let sibling_label = read_bytes(buf, pos)?;
+            path.push((went_left, balance, sibling_label));
+        }
+        Ok(RangeProofEntry { key, next_leaf_key, value, path })
+    }
+}
+
+pub(crate) fn encode_range_proof(entries: &[RangeProofEntry]) -> SerializedAdProof {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    for entry in entries {
+        entry.encode(&mut buf);
+    }
+    Bytes::from(buf)
+}
+
+pub(crate) fn decode_range_proof(proof: &SerializedAdProof) -> Result<Vec<RangeProofEntry>> {
+    let mut pos = 0usize;
+    let count = read_u32(proof, &mut pos)? as usize;
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        entries.push(RangeProofEntry::decode(proof, &mut pos)?);
+    }
+    Ok(entries)
+}
+
+/// True for the all-zero / all-`0xFF` sentinel keys that bound the tree
+/// on either side — never a real caller-inserted key (see
+/// `AVLTree::with_hash_fn`).
+pub(crate) fn is_sentinel_key(key: &[u8]) -> bool {
+    !key.is_empty() && (key.iter().all(|&b| b == 0x00) || key.iter().all(|&b| b == 0xFF))
+}
+
+fn write_bytes(buf: &mut Vec<u8>, data: &Bytes) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+fn read_bytes(buf: &[u8], pos: &mut usize) -> Result<Bytes> {
+    let len = read_u32(buf, pos)? as usize;
+    let slice = buf
+        .get(*pos..*pos + len)
+        .ok_or_else(|| anyhow!("range proof truncated: expected {len} more bytes at offset {pos}"))?;
+    let data = Bytes::copy_from_slice(slice);
+    *pos += len;
+    Ok(data)
+}
+
+fn read_u32(buf: &[u8], pos: &mut usize) -> Result<u32> {
+    let slice = buf
+        .get(*pos..*pos + 4)
+        .ok_or_else(|| anyhow!("range proof truncated: expected a 4-byte length at offset {pos}"))?;
+    let value = u32::from_be_bytes(slice.try_into().unwrap());
+    *pos += 4;
+    Ok(value)
+}
+
+fn read_byte(buf: &[u8], pos: &mut usize) -> Result<u8> {
+    let value = *buf
+        .get(*pos)
+        .ok_or_else(|| anyhow!("range proof truncated: expected a byte at offset {pos}"))?;
+    *pos += 1;
+    Ok(value)
+}
+
+/// Verifies a range proof produced by
+/// [`BatchAVLProver::generate_range_proof`](crate::batch_avl_prover::BatchAVLProver::generate_range_proof)
+/// against `initial_digest`, returning every key/value pair in
+/// `[lo, hi]`. Fails if the proof doesn't authenticate against the
+/// digest, if the `next_leaf_key` chain doesn't line up (a key in range
+/// was skipped), or if either boundary is missing.
+pub fn verify_range_proof(
+    initial_digest: &ADDigest,
+    proof: &SerializedAdProof,
+    lo: &ADKey,
+    hi: &ADKey,
+    hash_fn: &dyn HashFn,
+) -> Result<Vec<KeyValue>> {
+    if lo > hi {
+        return Err(anyhow!("empty range: lo > hi"));
+    }
+    if initial_digest.len() != 1 + hash_fn.output_len() {
+        return Err(anyhow!("unexpected digest length"));
+    }
+    let root_label = &initial_digest[1..];
+
+    let entries = decode_range_proof(proof)?;
+    let (boundary, rest) = entries.split_first().ok_or_else(|| anyhow!("empty range proof"))?;
+
+    if boundary.key >= *lo && !is_sentinel_key(&boundary.key) {
+        return Err(anyhow!("boundary leaf does not precede the range's lower bound"));
+    }
+    if boundary.implied_root_label(hash_fn).as_ref() != root_label {
+        return Err(anyhow!("boundary leaf does not authenticate against the digest"));
+    }
+    if rest.is_empty() {
+        return Err(anyhow!("range proof missing its upper boundary"));
+    }
+
+    let mut result = Vec::new();
+    let mut expected_key = boundary.next_leaf_key.clone();
+    let mut covered_up_to_hi = false;
+    for entry in rest {
+        if entry.key != expected_key {
+            return Err(anyhow!("next_leaf_key chain broken: a key in range was skipped"));
+        }
+        if entry.implied_root_label(hash_fn).as_ref() != root_label {
+            return Err(anyhow!("a leaf in the range proof does not authenticate against the digest"));
+        }
+        if entry.key <= *hi {
+            if !is_sentinel_key(&entry.key) {
+                result.push(KeyValue { key: entry.key.clone(), value: entry.value.clone() });
+            }
+        } else {
+            covered_up_to_hi = true;
+        }
+        if entry.key == entry.next_leaf_key {
+            // The positive-infinity sentinel points to itself: the walk
+            // has reached the end of the tree.
+            covered_up_to_hi = true;
+            break;
+        }
+        expected_key = entry.next_leaf_key.clone();
+    }
+
+    if !covered_up_to_hi {
+        return Err(anyhow!("range proof does not cover up to the upper bound"));
+    }
+    Ok(result)
+}
@@ -0,0 +1,36 @@
+//! Persistence for a [`BatchAVLProver`]'s node set across versions
+//! (typically: across blocks). Implementations decide how nodes and
+//! version metadata are physically stored; the prover only needs to be
+//! told "persist your current state" and "give me back the state as of
+//! version X".
+
+use crate::batch_avl_prover::BatchAVLProver;
+use crate::batch_node::{ADDigest, ADKey, ADValue, NodeId};
+use anyhow::Result;
+
+/// A durable, versioned store of a prover's node set.
+pub trait VersionedAVLStorage {
+    /// Reconstructs the `(root, height)` the prover had right after
+    /// `version` was written by `update`.
+    fn rollback(&mut self, version: &ADDigest) -> Result<(NodeId, usize)>;
+
+    /// Persists `prover`'s current state as a new version. `additional_data`
+    /// is arbitrary caller data (e.g. block metadata) stored alongside the
+    /// version, not interpreted by the storage itself.
+    fn update(&mut self, prover: &mut BatchAVLProver, additional_data: Vec<(ADKey, ADValue)>) -> Result<()>;
+
+    /// The most recently written version, if any.
+    fn version(&self) -> Option<ADDigest>;
+
+    /// All versions that can currently be rolled back to, most recent first.
+    fn rollback_versions<'a>(&'a self) -> Box<dyn Iterator<Item = ADDigest> + 'a>;
+
+    /// Drops nodes that belong only to versions older than the
+    /// `keep_versions` most recent roots (as ordered by
+    /// [`rollback_versions`](Self::rollback_versions)), so a
+    /// long-running prover doesn't retain every historical version
+    /// forever. The `keep_versions` most recent roots remain fully
+    /// rollback-able; older ones are no longer valid `rollback` targets.
+    /// Returns the number of nodes actually removed.
+    fn prune(&mut self, keep_versions: usize) -> Result<usize>;
+}
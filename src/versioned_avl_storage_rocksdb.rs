@@ -0,0 +1,354 @@
+//! A durable [`VersionedAVLStorage`] backed by RocksDB, for provers that
+//! need to survive a restart instead of rebuilding the whole tree in RAM.
+//!
+//! Nodes are kept in a `nodes` column family keyed by label (digest).
+//! `update` persists only the nodes created since the previous version —
+//! the rest of the tree is already on disk — by walking down from
+//! [`BatchAVLProver::top_node`] and stopping at the first node whose
+//! [`Node::is_new`] is false. Version metadata (root label, tree height,
+//! and a pointer to the previous version) lives in a separate `versions`
+//! column family, forming a singly-linked chain that `rollback_versions`
+//! walks back from the current head.
+//!
+//! Gated behind the `rocksdb_storage` feature so crates that don't need a
+//! persistent backend (and don't want the `rocksdb` dependency) aren't
+//! forced to build it.
+
+use crate::authenticated_tree_ops::*;
+use crate::batch_avl_prover::BatchAVLProver;
+use crate::batch_node::*;
+use crate::versioned_avl_storage::VersionedAVLStorage;
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use rocksdb::{ColumnFamilyDescriptor, IteratorMode, Options, DB};
+use std::cell::RefCell;
+use std::convert::TryInto;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+
+const NODES_CF: &str = "nodes";
+const VERSIONS_CF: &str = "versions";
+const REFCOUNTS_CF: &str = "refcounts";
+const HEAD_KEY: &[u8] = b"head";
+
+pub struct RocksDBVersionedAVLStorage {
+    db: Arc<DB>,
+}
+
+impl RocksDBVersionedAVLStorage {
+    pub fn open(path: &Path) -> Result<RocksDBVersionedAVLStorage> {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let cfs = vec![
+            ColumnFamilyDescriptor::new(NODES_CF, Options::default()),
+            ColumnFamilyDescriptor::new(VERSIONS_CF, Options::default()),
+            ColumnFamilyDescriptor::new(REFCOUNTS_CF, Options::default()),
+        ];
+        let db = DB::open_cf_descriptors(&opts, path, cfs)
+            .with_context(|| format!("opening RocksDB store at {}", path.display()))?;
+        Ok(RocksDBVersionedAVLStorage { db: Arc::new(db) })
+    }
+
+    /// A resolver that fetches a node's contents from the `nodes` column
+    /// family on demand. Use it as the `resolver` of the [`AVLTree`]
+    /// built around the `LabelOnly` root that `rollback` returns, so only
+    /// the path a caller actually walks gets deserialized.
+    pub fn resolver(&self) -> Resolver {
+        let db = self.db.clone();
+        Rc::new(move |label: &Digest32| {
+            let cf = db.cf_handle(NODES_CF).expect("nodes column family must exist");
+            let raw = db
+                .get_cf(cf, label)
+                .expect("RocksDB read failed")
+                .unwrap_or_else(|| panic!("node {:x?} missing from storage", label.as_ref()));
+            decode_node(label, &raw)
+        })
+    }
+
+    fn nodes_cf(&self) -> Result<&rocksdb::ColumnFamily> {
+        self.db.cf_handle(NODES_CF).ok_or_else(|| anyhow!("missing '{}' column family", NODES_CF))
+    }
+
+    fn versions_cf(&self) -> Result<&rocksdb::ColumnFamily> {
+        self.db
+            .cf_handle(VERSIONS_CF)
+            .ok_or_else(|| anyhow!("missing '{}' column family", VERSIONS_CF))
+    }
+
+    fn refcounts_cf(&self) -> Result<&rocksdb::ColumnFamily> {
+        self.db
+            .cf_handle(REFCOUNTS_CF)
+            .ok_or_else(|| anyhow!("missing '{}' column family", REFCOUNTS_CF))
+    }
+
+    /// Writes `node` and every descendant still marked new, clearing the
+    /// flag as it goes. Stops descending as soon as a node isn't new,
+    /// since that means its whole subtree was already persisted. Every
+    /// child gained a new parent edge in the process, so its reference
+    /// count goes up by one whether or not the child itself is new —
+    /// that's what lets a shared, unchanged subtree outlive the version
+    /// that stopped pointing at it.
+    fn persist_new_nodes(&self, node: &NodeId) -> Result<usize> {
+        if !node.borrow().is_new() {
+            return Ok(0);
+        }
+        let children: Vec<NodeId> = match &*node.borrow() {
+            Node::Internal(i) => vec![i.left.clone(), i.right.clone()],
+            _ => Vec::new(),
+        };
+        let mut written = 0;
+        for child in &children {
+            written += self.persist_new_nodes(child)?;
+            let child_label = child.borrow().get_label().clone();
+            self.bump_refcount(&child_label, 1)?;
+        }
+
+        let label = node.borrow().get_label().clone();
+        self.db.put_cf(self.nodes_cf()?, &label, encode_node(&node.borrow()))?;
+        node.borrow_mut().header_mut().is_new = false;
+        Ok(written + 1)
+    }
+
+    fn bump_refcount(&self, label: &Digest32, delta: i64) -> Result<i64> {
+        let cf = self.refcounts_cf()?;
+        let current = match self.db.get_cf(cf, label)? {
+            Some(raw) => i64::from_be_bytes(raw.as_slice().try_into()?),
+            None => 0,
+        };
+        let updated = current + delta;
+        self.db.put_cf(cf, label, updated.to_be_bytes())?;
+        Ok(updated)
+    }
+
+    /// Drops this version's reference to `label`. If that was the last
+    /// reference, deletes the node and recurses into its children,
+    /// releasing their references in turn.
+    fn release(&self, label: &Digest32) -> Result<usize> {
+        if self.bump_refcount(label, -1)? > 0 {
+            return Ok(0);
+        }
+        let raw = self.db.get_cf(self.nodes_cf()?, label)?;
+        let mut removed = 1;
+        if let Some(raw) = &raw {
+            if raw[0] == 1 {
+                let (left_label, right_label) = decode_internal_children_labels(raw);
+                removed += self.release(&left_label)?;
+                removed += self.release(&right_label)?;
+            }
+        }
+        self.db.delete_cf(self.nodes_cf()?, label)?;
+        self.db.delete_cf(self.refcounts_cf()?, label)?;
+        Ok(removed)
+    }
+}
+
+impl VersionedAVLStorage for RocksDBVersionedAVLStorage {
+    fn rollback(&mut self, version: &ADDigest) -> Result<(NodeId, usize)> {
+        let raw = self
+            .db
+            .get_cf(self.versions_cf()?, version)?
+            .ok_or_else(|| anyhow!("version not found"))?;
+        let record = VersionRecord::decode(&raw);
+        let root = Rc::new(RefCell::new(Node::LabelOnly(NodeHeader::new(Some(record.root_label), None))));
+        // `update` chains the next version's `prev` onto whatever HEAD_KEY
+        // currently holds, so it must track the version actually rolled
+        // back to, not just the version last `update`d.
+        self.db.put_cf(self.versions_cf()?, HEAD_KEY, version)?;
+        Ok((root, record.height))
+    }
+
+    fn update(&mut self, prover: &mut BatchAVLProver, additional_data: Vec<(ADKey, ADValue)>) -> Result<()> {
+        let _ = additional_data; // caller-supplied metadata; not interpreted by this backend
+
+        let top = prover.top_node();
+        self.persist_new_nodes(&top)
+            .context("persisting nodes newly created since the last version")?;
+
+        let digest = prover.digest().ok_or_else(|| anyhow!("prover has no digest yet"))?;
+        let root_label = top.borrow().get_label().clone();
+        // The version itself is a reference to its root, same as an
+        // internal node referencing a child — this is what `prune`
+        // releases once the version falls outside the retention window.
+        self.bump_refcount(&root_label, 1)?;
+
+        let prev = self.version();
+        let record = VersionRecord {
+            height: prover.get_tree().height,
+            root_label,
+            prev,
+        };
+        self.db.put_cf(self.versions_cf()?, &digest, record.encode())?;
+        self.db.put_cf(self.versions_cf()?, HEAD_KEY, &digest)?;
+        Ok(())
+    }
+
+    fn version(&self) -> Option<ADDigest> {
+        let cf = self.versions_cf().ok()?;
+        self.db.get_cf(cf, HEAD_KEY).ok()?.map(Bytes::from)
+    }
+
+    fn rollback_versions<'a>(&'a self) -> Box<dyn Iterator<Item = ADDigest> + 'a> {
+        Box::new(VersionChainIterator {
+            db: &self.db,
+            current: self.version(),
+        })
+    }
+
+    fn prune(&mut self, keep_versions: usize) -> Result<usize> {
+        let chain: Vec<ADDigest> = self.rollback_versions().collect();
+        if chain.len() <= keep_versions {
+            return Ok(0);
+        }
+        let (retained, stale) = chain.split_at(keep_versions);
+
+        let mut removed = 0;
+        for version in stale {
+            let raw = self
+                .db
+                .get_cf(self.versions_cf()?, version)?
+                .ok_or_else(|| anyhow!("version not found"))?;
+            let record = VersionRecord::decode(&raw);
+            removed += self.release(&record.root_label)?;
+            self.db.delete_cf(self.versions_cf()?, version)?;
+        }
+
+        // Terminate the chain at the oldest version we kept, so
+        // `rollback_versions` doesn't walk into the versions we just
+        // deleted.
+        if let Some(oldest_retained) = retained.last() {
+            let raw = self
+                .db
+                .get_cf(self.versions_cf()?, oldest_retained)?
+                .ok_or_else(|| anyhow!("version not found"))?;
+            let mut record = VersionRecord::decode(&raw);
+            record.prev = None;
+            self.db.put_cf(self.versions_cf()?, oldest_retained, record.encode())?;
+        }
+
+        Ok(removed)
+    }
+}
+
+fn decode_internal_children_labels(raw: &[u8]) -> (Digest32, Digest32) {
+    let mut pos = 2;
+    let _routing_key = read_length_prefixed(raw, &mut pos);
+    let left_label = Bytes::copy_from_slice(&raw[pos..pos + LABEL_SIZE]);
+    pos += LABEL_SIZE;
+    let right_label = Bytes::copy_from_slice(&raw[pos..pos + LABEL_SIZE]);
+    (left_label, right_label)
+}
+
+struct VersionChainIterator<'a> {
+    db: &'a DB,
+    current: Option<ADDigest>,
+}
+
+impl<'a> Iterator for VersionChainIterator<'a> {
+    type Item = ADDigest;
+
+    fn next(&mut self) -> Option<ADDigest> {
+        let cur = self.current.take()?;
+        let cf = self.db.cf_handle(VERSIONS_CF)?;
+        let raw = self.db.get_cf(cf, &cur).ok()??;
+        self.current = VersionRecord::decode(&raw).prev;
+        Some(cur)
+    }
+}
+
+struct VersionRecord {
+    height: usize,
+    root_label: Digest32,
+    prev: Option<ADDigest>,
+}
+
+impl VersionRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4 + self.root_label.len() + 1 + self.prev.as_ref().map_or(0, |p| p.len()));
+        buf.extend_from_slice(&(self.height as u32).to_be_bytes());
+        buf.extend_from_slice(&self.root_label);
+        match &self.prev {
+            Some(p) => {
+                buf.push(1);
+                buf.extend_from_slice(p);
+            }
+            None => buf.push(0),
+        }
+        buf
+    }
+
+    fn decode(raw: &[u8]) -> VersionRecord {
+        let height = u32::from_be_bytes(raw[0..4].try_into().unwrap()) as usize;
+        let root_label = Bytes::copy_from_slice(&raw[4..4 + LABEL_SIZE]);
+        let rest = &raw[4 + LABEL_SIZE..];
+        let prev = match rest[0] {
+            1 => Some(Bytes::copy_from_slice(&rest[1..])),
+            _ => None,
+        };
+        VersionRecord { height, root_label, prev }
+    }
+}
+
+fn encode_node(node: &Node) -> Vec<u8> {
+    match node {
+        Node::Leaf(l) => {
+            let mut buf = vec![0u8];
+            write_length_prefixed(&mut buf, &l.key);
+            write_length_prefixed(&mut buf, &l.next_leaf_key);
+            write_length_prefixed(&mut buf, &l.value);
+            buf
+        }
+        Node::Internal(i) => {
+            let mut buf = vec![1u8, i.balance as u8];
+            write_length_prefixed(&mut buf, &i.routing_key);
+            buf.extend_from_slice(i.left.borrow().get_label());
+            buf.extend_from_slice(i.right.borrow().get_label());
+            buf
+        }
+        Node::LabelOnly(_) => panic!("attempted to persist an unresolved node"),
+    }
+}
+
+fn decode_node(label: &Digest32, raw: &[u8]) -> Node {
+    let mut pos = 1;
+    match raw[0] {
+        0 => {
+            let key = read_length_prefixed(raw, &mut pos);
+            let next_leaf_key = read_length_prefixed(raw, &mut pos);
+            let value = read_length_prefixed(raw, &mut pos);
+            let mut node = Node::new_leaf(key, value, next_leaf_key);
+            node.header_mut().label = Some(label.clone());
+            node.header_mut().is_new = false; // already on disk
+            node
+        }
+        1 => {
+            let balance = raw[1] as i8;
+            pos = 2;
+            let routing_key = read_length_prefixed(raw, &mut pos);
+            let left_label = Bytes::copy_from_slice(&raw[pos..pos + LABEL_SIZE]);
+            pos += LABEL_SIZE;
+            let right_label = Bytes::copy_from_slice(&raw[pos..pos + LABEL_SIZE]);
+            let left = Rc::new(RefCell::new(Node::LabelOnly(NodeHeader::new(Some(left_label), None))));
+            let right = Rc::new(RefCell::new(Node::LabelOnly(NodeHeader::new(Some(right_label), None))));
+            let mut node = Node::new_internal(left, right, balance, routing_key);
+            node.header_mut().label = Some(label.clone());
+            node.header_mut().is_new = false; // already on disk
+            node
+        }
+        tag => panic!("corrupt node encoding: unknown tag {}", tag),
+    }
+}
+
+fn write_length_prefixed(buf: &mut Vec<u8>, data: &Bytes) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+fn read_length_prefixed(buf: &[u8], pos: &mut usize) -> Bytes {
+    let len = u32::from_be_bytes(buf[*pos..*pos + 4].try_into().unwrap()) as usize;
+    *pos += 4;
+    let data = Bytes::copy_from_slice(&buf[*pos..*pos + len]);
+    *pos += len;
+    data
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::*;
+use ergo_avltree_rust::batch_avl_prover::ApplyOptions;
+use ergo_avltree_rust::operation::*;
+
+#[test]
+fn strict_insert_batch_rolls_back_on_existing_key() {
+    let (mut prover, initial_elements) = generate_and_populate_prover(INITIAL_TREE_SIZE);
+    let digest_before = prover.digest().unwrap();
+
+    let conflicting = initial_elements[0].clone();
+    let fresh = random_kv();
+    let ops = vec![
+        Operation::Insert(fresh.clone()),
+        Operation::Insert(conflicting),
+        Operation::Insert(random_kv()),
+    ];
+
+    let result = prover.perform_operations(&ops, &ApplyOptions::default());
+    let err = result.expect_err("batch should fail: op 1 inserts an existing key");
+    assert_eq!(err.failed_operation_index, 1);
+
+    assert_eq!(prover.digest().unwrap(), digest_before);
+    assert!(prover
+        .perform_one_operation(&Operation::Lookup(fresh.key))
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn failed_batch_does_not_leak_stale_nodes_into_next_proof() {
+    let (mut prover, initial_elements) = generate_and_populate_prover(10);
+
+    let mut control = generate_prover(KEY_LENGTH, None);
+    for kv in &initial_elements {
+        control.perform_one_operation(&Operation::Insert(kv.clone())).unwrap();
+    }
+    control.generate_proof();
+
+    let fresh = random_kv();
+    let conflicting = initial_elements[0].clone();
+    let ops = vec![Operation::Insert(fresh.clone()), Operation::Insert(conflicting)];
+    let err = prover
+        .perform_operations(&ops, &ApplyOptions::default())
+        .expect_err("batch should fail: op 1 inserts an existing key");
+    assert_eq!(err.failed_operation_index, 1);
+
+    // Both provers now perform the exact same lone real operation — the one
+    // that was op 0 of the rolled-back batch — from otherwise identical
+    // states. If the failed batch left any of its own changed/deleted node
+    // entries behind, `prover`'s proof would include them on top of this
+    // and come out longer than `control`'s.
+    prover.perform_one_operation(&Operation::Insert(fresh.clone())).unwrap();
+    control.perform_one_operation(&Operation::Insert(fresh)).unwrap();
+    assert_eq!(prover.generate_proof(), control.generate_proof());
+}
+
+#[test]
+fn allow_insert_or_update_treats_existing_keys_as_updates() {
+    let (mut prover, initial_elements) = generate_and_populate_prover(10);
+    let existing = initial_elements[0].clone();
+    let updated_value = random_value();
+
+    let ops = vec![Operation::Insert(KeyValue {
+        key: existing.key.clone(),
+        value: updated_value.clone(),
+    })];
+    let options = ApplyOptions {
+        allow_insert_or_update: true,
+        ..ApplyOptions::default()
+    };
+
+    let results = prover.perform_operations(&ops, &options).unwrap();
+    assert_eq!(results, vec![Some(existing.value)]);
+    assert_eq!(
+        prover.perform_one_operation(&Operation::Lookup(existing.key)).unwrap(),
+        Some(updated_value)
+    );
+}
+
+#[test]
+fn validate_insertion_does_not_override_wins_over_allow_insert_or_update() {
+    let (mut prover, initial_elements) = generate_and_populate_prover(10);
+    let existing = initial_elements[0].clone();
+
+    let ops = vec![Operation::Insert(existing)];
+    let options = ApplyOptions {
+        allow_insert_or_update: true,
+        validate_insertion_does_not_override: true,
+        ..ApplyOptions::default()
+    };
+
+    let err = prover
+        .perform_operations(&ops, &options)
+        .expect_err("validate_insertion_does_not_override should force strict Insert semantics");
+    assert_eq!(err.failed_operation_index, 0);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::*;
+use ergo_avltree_rust::operation::*;
+
+#[test]
+fn verifier_replays_a_batch_of_lookups_against_one_proof() {
+    let (prover, mut elements) = generate_and_populate_prover(INITIAL_TREE_SIZE);
+    elements.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let present = elements[100].clone();
+    let missing = random_key();
+    let keys = vec![present.key.clone(), missing.clone()];
+
+    let digest = prover.digest().unwrap();
+    let proof = prover.generate_lookup_proof(&keys);
+
+    let mut verifier = generate_verifier(&digest, &proof, None, None);
+    assert_eq!(
+        verifier.perform_one_operation(&Operation::Lookup(present.key)).unwrap(),
+        Some(present.value)
+    );
+    assert_eq!(verifier.perform_one_operation(&Operation::Lookup(missing)).unwrap(), None);
+    assert_eq!(verifier.digest(), digest);
+}
+
+#[test]
+fn verifier_rejects_a_tampered_proof() {
+    let (prover, elements) = generate_and_populate_prover(INITIAL_TREE_SIZE);
+    let target = elements[0].clone();
+
+    let digest = prover.digest().unwrap();
+    let proof = prover.generate_lookup_proof(std::slice::from_ref(&target.key));
+
+    let mut tampered = proof.to_vec();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 0xFF;
+
+    let result = ergo_avltree_rust::batch_avl_verifier::BatchAVLVerifier::new(
+        &digest,
+        &bytes::Bytes::from(tampered),
+        std::rc::Rc::new(ergo_avltree_rust::batch_node::Blake2b256Hash),
+        None,
+        None,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn verifier_errors_once_the_proof_is_exhausted() {
+    let (prover, elements) = generate_and_populate_prover(INITIAL_TREE_SIZE);
+    let target = elements[0].clone();
+
+    let digest = prover.digest().unwrap();
+    let proof = prover.generate_lookup_proof(std::slice::from_ref(&target.key));
+
+    let mut verifier = generate_verifier(&digest, &proof, None, None);
+    assert!(verifier.perform_one_operation(&Operation::Lookup(target.key)).is_ok());
+    assert!(verifier.perform_one_operation(&Operation::Lookup(random_key())).is_err());
+}
+
+#[test]
+fn verifier_rejects_mutating_operations() {
+    let (prover, _elements) = generate_and_populate_prover(10);
+    let digest = prover.digest().unwrap();
+    let proof = prover.generate_lookup_proof(&[]);
+
+    let mut verifier = generate_verifier(&digest, &proof, None, None);
+    assert!(verifier.perform_one_operation(&Operation::Remove(random_key())).is_err());
+}
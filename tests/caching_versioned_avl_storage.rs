@@ -0,0 +1,115 @@
+mod common;
+
+use common::*;
+use ergo_avltree_rust::batch_avl_prover::BatchAVLProver;
+use ergo_avltree_rust::batch_node::Blake2b256Hash;
+use ergo_avltree_rust::caching_versioned_avl_storage::CachingVersionedAVLStorage;
+use ergo_avltree_rust::operation::*;
+use ergo_avltree_rust::versioned_avl_storage::VersionedAVLStorage;
+use std::rc::Rc;
+
+fn new_cache(keep_versions: usize) -> CachingVersionedAVLStorage<VersionedAVLStorageMock> {
+    CachingVersionedAVLStorage::new(
+        VersionedAVLStorageMock::new(),
+        keep_versions,
+        KEY_LENGTH,
+        None,
+        Rc::new(Blake2b256Hash),
+    )
+}
+
+#[test]
+fn rollback_serves_recent_versions_from_the_cache() {
+    let mut storage = new_cache(10);
+    let mut prover = generate_prover(KEY_LENGTH, None);
+
+    prover.perform_one_operation(&Operation::Insert(random_kv())).unwrap();
+    storage.update(&mut prover, Vec::new()).unwrap();
+    let v1 = storage.version().unwrap();
+
+    prover.perform_one_operation(&Operation::Insert(random_kv())).unwrap();
+    storage.update(&mut prover, Vec::new()).unwrap();
+    let v2 = storage.version().unwrap();
+    assert_ne!(v1, v2);
+
+    // Nothing has been flushed to the backend yet, so a cache hit is the
+    // only way either version could possibly resolve.
+    storage.rollback(&v1).unwrap();
+    assert_eq!(storage.version().unwrap(), v1);
+
+    storage.rollback(&v2).unwrap();
+    assert_eq!(storage.version().unwrap(), v2);
+}
+
+#[test]
+fn update_after_rollback_keeps_both_branches_reachable() {
+    let mut storage = new_cache(10);
+    let mut prover = generate_prover(KEY_LENGTH, None);
+
+    prover.perform_one_operation(&Operation::Insert(random_kv())).unwrap();
+    storage.update(&mut prover, Vec::new()).unwrap();
+    let v1 = storage.version().unwrap();
+
+    prover.perform_one_operation(&Operation::Insert(random_kv())).unwrap();
+    storage.update(&mut prover, Vec::new()).unwrap();
+    let v2_original = storage.version().unwrap();
+
+    // Roll back to v1 and diverge onto a second branch.
+    let (root, height) = storage.rollback(&v1).unwrap();
+    let mut forked_tree = generate_tree(KEY_LENGTH, None);
+    forked_tree.root = root;
+    forked_tree.height = height;
+    let mut forked_prover = BatchAVLProver::new(forked_tree, true);
+    forked_prover
+        .perform_one_operation(&Operation::Insert(random_kv()))
+        .unwrap();
+    storage.update(&mut forked_prover, Vec::new()).unwrap();
+    let v2_fork = storage.version().unwrap();
+    assert_ne!(v2_fork, v2_original);
+
+    // Both tips of the fork, and their shared ancestor, must still resolve.
+    assert!(storage.rollback(&v2_original).is_ok());
+    assert!(storage.rollback(&v2_fork).is_ok());
+    assert!(storage.rollback(&v1).is_ok());
+}
+
+#[test]
+fn branches_age_out_once_the_retention_window_passes() {
+    let mut storage = new_cache(2);
+    let mut prover = generate_prover(KEY_LENGTH, None);
+
+    prover.perform_one_operation(&Operation::Insert(random_kv())).unwrap();
+    storage.update(&mut prover, Vec::new()).unwrap();
+    let v1 = storage.version().unwrap();
+
+    // Two more versions on the same branch push v1 outside the
+    // keep_versions = 2 window.
+    for _ in 0..2 {
+        prover.perform_one_operation(&Operation::Insert(random_kv())).unwrap();
+        storage.update(&mut prover, Vec::new()).unwrap();
+    }
+
+    // v1 was never flushed to the backend, so once it's evicted from the
+    // cache it's gone entirely.
+    assert!(storage.rollback(&v1).is_err());
+}
+
+#[test]
+fn commit_to_backend_flushes_the_canonical_chain() {
+    let mut storage = new_cache(10);
+    let mut prover = generate_prover(KEY_LENGTH, None);
+
+    prover.perform_one_operation(&Operation::Insert(random_kv())).unwrap();
+    storage.update(&mut prover, Vec::new()).unwrap();
+    let v1 = storage.version().unwrap();
+
+    prover.perform_one_operation(&Operation::Insert(random_kv())).unwrap();
+    storage.update(&mut prover, Vec::new()).unwrap();
+    let v2 = storage.version().unwrap();
+
+    storage.commit_to_backend().unwrap();
+
+    // Rolling back within the now-committed chain still works post-commit.
+    assert!(storage.rollback(&v1).is_ok());
+    assert!(storage.rollback(&v2).is_ok());
+}
@@ -1,5 +1,9 @@
+// This module is shared, via `mod common;`, by every integration test
+// binary; each one only exercises a subset of these helpers, so the rest
+// are legitimately unused from that binary's point of view.
+#![allow(dead_code)]
+
 use anyhow::*;
-use blake2::digest::Digest as _;
 use bytes::Bytes;
 use ergo_avltree_rust::authenticated_tree_ops::*;
 use ergo_avltree_rust::batch_avl_prover::*;
@@ -21,7 +25,7 @@ pub const MAX_KEY: [u8; KEY_LENGTH] = [0xFFu8; KEY_LENGTH];
 
 pub fn random_key_with_len(len: usize) -> ADKey {
     let key = (0..len).map(|_| rand::random::<u8>()).collect();
-    if key < Bytes::from(MIN_KEY.to_vec()) || key > Bytes::from(MAX_KEY.to_vec()) {
+    if key < MIN_KEY.to_vec() || key > MAX_KEY.to_vec() {
         random_key_with_len(len)
     } else {
         key
@@ -60,7 +64,7 @@ pub fn generate_kv_list(size: usize) -> Vec<KeyValue> {
     (0..size)
         .map(|i| {
             let mut hasher = Blake2b256::new();
-            hasher.update(&i.to_string());
+            hasher.update(i.to_string());
             let key = Bytes::copy_from_slice(&hasher.finalize());
             let value = key.clone();
             KeyValue { key, value }
@@ -81,23 +85,14 @@ fn dummy_resolver(digest: &Digest32) -> Node {
 pub fn generate_verifier(
     initial_digest: &ADDigest,
     proof: &SerializedAdProof,
-    key_length: usize,
-    value_length: Option<usize>,
     max_num_operations: Option<usize>,
     max_deletes: Option<usize>,
 ) -> BatchAVLVerifier {
-    BatchAVLVerifier::new(
-        initial_digest,
-        proof,
-        generate_tree(key_length, value_length),
-        max_num_operations,
-        max_deletes,
-    )
-    .unwrap()
+    BatchAVLVerifier::new(initial_digest, proof, std::rc::Rc::new(Blake2b256Hash), max_num_operations, max_deletes).unwrap()
 }
 
 pub fn generate_tree(key_length: usize, value_length: Option<usize>) -> AVLTree {
-    AVLTree::new(dummy_resolver, key_length, value_length)
+    AVLTree::new(std::rc::Rc::new(dummy_resolver), key_length, value_length)
 }
 
 pub fn generate_prover(key_length: usize, value_length: Option<usize>) -> BatchAVLProver {
@@ -109,7 +104,7 @@ pub fn generate_and_populate_prover(size: usize) -> (BatchAVLProver, Vec<KeyValu
     let mut initial_elements: Vec<KeyValue> = Vec::new();
     for i in 0..size {
         let mut hasher = Blake2b256::new();
-        hasher.update(&i.to_string());
+        hasher.update(i.to_string());
         let key = Bytes::copy_from_slice(&hasher.finalize());
         let value = Bytes::from(i.to_string());
         let kv = KeyValue { key, value };
@@ -135,12 +130,9 @@ fn check_removed(prover: &mut BatchAVLProver, node: &NodeId, removed_nodes: &Vec
     );
 
     let n = node.borrow().clone();
-    match n {
-        Node::Internal(i) => {
-            removed += check_removed(prover, &i.left, removed_nodes);
-            removed += check_removed(prover, &i.right, removed_nodes);
-        }
-        _ => {}
+    if let Node::Internal(i) = n {
+        removed += check_removed(prover, &i.left, removed_nodes);
+        removed += check_removed(prover, &i.right, removed_nodes);
     }
     removed
 }
@@ -211,6 +203,20 @@ impl VersionedAVLStorage for VersionedAVLStorageMock {
             version: self.v.clone(),
         })
     }
+    fn prune(&mut self, keep_versions: usize) -> Result<usize> {
+        // The mock only ever exposes the current version through
+        // `rollback_versions`, so anything else accumulated in
+        // `saved_nodes` lies outside the retention window and is dropped
+        // once asked to keep at least one version. Returns the number of
+        // saved versions dropped.
+        if keep_versions == 0 {
+            return Ok(0);
+        }
+        let current = self.v.clone();
+        let before = self.saved_nodes.len();
+        self.saved_nodes.retain(|version, _| Some(version.clone()) == current);
+        Ok(before - self.saved_nodes.len())
+    }
 }
 
 struct RollbackVersionIterator {
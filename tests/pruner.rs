@@ -0,0 +1,144 @@
+#![cfg(feature = "rocksdb_storage")]
+
+mod common;
+
+use common::*;
+use ergo_avltree_rust::operation::*;
+use ergo_avltree_rust::versioned_avl_storage::VersionedAVLStorage;
+use ergo_avltree_rust::versioned_avl_storage_rocksdb::RocksDBVersionedAVLStorage;
+
+struct TempDir(std::path::PathBuf);
+
+impl TempDir {
+    fn new(label: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("ergo-avltree-rust-{}-{}", label, std::process::id()));
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn prune_keeps_retained_versions_and_drops_the_rest() {
+    let dir = TempDir::new("prune");
+    let mut storage = RocksDBVersionedAVLStorage::open(&dir.0).unwrap();
+    let mut prover = generate_prover(KEY_LENGTH, None);
+
+    const KEEP: usize = 3;
+    const TOTAL_VERSIONS: usize = 6;
+    let mut versions = Vec::new();
+    for _ in 0..TOTAL_VERSIONS {
+        let kv = random_kv();
+        prover.perform_one_operation(&Operation::Insert(kv)).unwrap();
+        prover.generate_proof();
+        storage.update(&mut prover, Vec::new()).unwrap();
+        versions.push(storage.version().unwrap());
+    }
+
+    let removed = storage.prune(KEEP).unwrap();
+    assert!(removed > 0);
+
+    let retained: Vec<_> = storage.rollback_versions().collect();
+    assert_eq!(retained.len(), KEEP);
+    assert_eq!(&retained[..], &versions[TOTAL_VERSIONS - KEEP..].iter().rev().cloned().collect::<Vec<_>>()[..]);
+
+    for version in &retained {
+        assert!(storage.rollback(version).is_ok());
+    }
+    for version in &versions[..TOTAL_VERSIONS - KEEP] {
+        assert!(storage.rollback(version).is_err());
+    }
+}
+
+#[test]
+fn prune_drops_versions_whose_removed_nodes_are_unreachable() {
+    let dir = TempDir::new("prune-removed");
+    let mut storage = RocksDBVersionedAVLStorage::open(&dir.0).unwrap();
+    let mut prover = generate_prover(KEY_LENGTH, None);
+
+    let kvs: Vec<_> = (0..5).map(|_| random_kv()).collect();
+    for kv in &kvs {
+        prover.perform_one_operation(&Operation::Insert(kv.clone())).unwrap();
+    }
+    prover.generate_proof();
+    storage.update(&mut prover, Vec::new()).unwrap();
+
+    let old_top = prover.top_node();
+    prover.perform_one_operation(&Operation::Remove(kvs[0].key.clone())).unwrap();
+    let removed_nodes = prover.deleted_nodes().to_vec();
+    prover.generate_proof();
+    storage.update(&mut prover, Vec::new()).unwrap();
+
+    // `old_top` still references the pre-removal nodes directly (the
+    // prover mutates new `Rc`s into place rather than the old ones), so
+    // this confirms removal actually dropped `removed_nodes` from the
+    // tree and nothing else reachable from `old_top` went missing
+    // alongside them.
+    check_tree(&mut prover, &old_top, &removed_nodes);
+
+    assert_eq!(storage.prune(1).unwrap(), 1);
+    assert_eq!(storage.rollback_versions().count(), 1);
+}
+
+#[test]
+fn rollback_then_update_chains_onto_the_rolled_back_version() {
+    let dir = TempDir::new("prune-rollback-chain");
+    let mut storage = RocksDBVersionedAVLStorage::open(&dir.0).unwrap();
+    let mut prover = generate_prover(KEY_LENGTH, None);
+
+    let kv1 = random_kv();
+    prover.perform_one_operation(&Operation::Insert(kv1)).unwrap();
+    prover.generate_proof();
+    storage.update(&mut prover, Vec::new()).unwrap();
+    let v1 = storage.version().unwrap();
+
+    let kv2 = random_kv();
+    prover.perform_one_operation(&Operation::Insert(kv2)).unwrap();
+    prover.generate_proof();
+    storage.update(&mut prover, Vec::new()).unwrap();
+    let v2 = storage.version().unwrap();
+    assert_ne!(v1, v2);
+
+    // Roll back to v1 and diverge onto a new branch from there.
+    let (root, height) = storage.rollback(&v1).unwrap();
+    let forked_tree = ergo_avltree_rust::authenticated_tree_ops::AVLTree {
+        root,
+        height,
+        key_length: KEY_LENGTH,
+        value_length_opt: None,
+        resolver: storage.resolver(),
+        hash_fn: std::rc::Rc::new(ergo_avltree_rust::batch_node::Blake2b256Hash),
+    };
+    let mut forked_prover = ergo_avltree_rust::batch_avl_prover::BatchAVLProver::new(forked_tree, true);
+    forked_prover.perform_one_operation(&Operation::Insert(random_kv())).unwrap();
+    forked_prover.generate_proof();
+    storage.update(&mut forked_prover, Vec::new()).unwrap();
+    let v3 = storage.version().unwrap();
+
+    // v3's chain must run through v1, the version it was actually built
+    // on, not through the abandoned v2 that merely happened to be HEAD
+    // before the rollback.
+    let chain: Vec<_> = storage.rollback_versions().collect();
+    assert_eq!(chain, vec![v3, v1]);
+}
+
+#[test]
+fn prune_is_a_no_op_within_the_retention_window() {
+    let dir = TempDir::new("prune-noop");
+    let mut storage = RocksDBVersionedAVLStorage::open(&dir.0).unwrap();
+    let mut prover = generate_prover(KEY_LENGTH, None);
+
+    for _ in 0..2 {
+        let kv = random_kv();
+        prover.perform_one_operation(&Operation::Insert(kv)).unwrap();
+        prover.generate_proof();
+        storage.update(&mut prover, Vec::new()).unwrap();
+    }
+
+    assert_eq!(storage.prune(10).unwrap(), 0);
+    assert_eq!(storage.rollback_versions().count(), 2);
+}
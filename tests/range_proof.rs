@@ -0,0 +1,116 @@
+mod common;
+
+use bytes::Bytes;
+use common::*;
+use ergo_avltree_rust::batch_node::Blake2b256Hash;
+use ergo_avltree_rust::operation::*;
+use ergo_avltree_rust::range_proof::verify_range_proof;
+
+#[test]
+fn range_proof_authenticates_exactly_the_keys_in_range() {
+    let (prover, mut elements) = generate_and_populate_prover(INITIAL_TREE_SIZE);
+    elements.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let lo = elements[200].key.clone();
+    let hi = elements[210].key.clone();
+    let expected: Vec<KeyValue> = elements[200..=210].to_vec();
+
+    let digest = prover.digest().unwrap();
+    let (returned, proof) = prover.generate_range_proof(&lo, &hi).unwrap();
+    assert_eq!(returned, expected);
+
+    let verified = verify_range_proof(&digest, &proof, &lo, &hi, &Blake2b256Hash).unwrap();
+    assert_eq!(verified, expected);
+}
+
+#[test]
+fn range_proof_works_when_bounds_fall_between_existing_keys() {
+    let (prover, mut elements) = generate_and_populate_prover(INITIAL_TREE_SIZE);
+    elements.sort_by(|a, b| a.key.cmp(&b.key));
+
+    // `lo`/`hi` sit strictly between two present keys, neither is itself present.
+    let lo = midpoint(&elements[50].key, &elements[51].key);
+    let hi = midpoint(&elements[60].key, &elements[61].key);
+    let expected: Vec<KeyValue> = elements[51..=60].to_vec();
+
+    let digest = prover.digest().unwrap();
+    let (returned, proof) = prover.generate_range_proof(&lo, &hi).unwrap();
+    assert_eq!(returned, expected);
+
+    let verified = verify_range_proof(&digest, &proof, &lo, &hi, &Blake2b256Hash).unwrap();
+    assert_eq!(verified, expected);
+}
+
+#[test]
+fn empty_range_between_adjacent_keys_yields_no_pairs_but_a_valid_proof() {
+    let (prover, mut elements) = generate_and_populate_prover(INITIAL_TREE_SIZE);
+    elements.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let lo = midpoint(&elements[30].key, &elements[31].key);
+    let hi = lo.clone();
+
+    let digest = prover.digest().unwrap();
+    let (returned, proof) = prover.generate_range_proof(&lo, &hi).unwrap();
+    assert!(returned.is_empty());
+
+    let verified = verify_range_proof(&digest, &proof, &lo, &hi, &Blake2b256Hash).unwrap();
+    assert!(verified.is_empty());
+}
+
+#[test]
+fn range_touching_the_min_and_max_sentinels_returns_every_key() {
+    let (prover, mut elements) = generate_and_populate_prover(50);
+    elements.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let lo = Bytes::from(MIN_KEY.to_vec());
+    let hi = Bytes::from(MAX_KEY.to_vec());
+
+    let digest = prover.digest().unwrap();
+    let (returned, proof) = prover.generate_range_proof(&lo, &hi).unwrap();
+    assert_eq!(returned, elements);
+
+    let verified = verify_range_proof(&digest, &proof, &lo, &hi, &Blake2b256Hash).unwrap();
+    assert_eq!(verified, elements);
+}
+
+#[test]
+fn tampered_proof_fails_verification() {
+    let (prover, mut elements) = generate_and_populate_prover(INITIAL_TREE_SIZE);
+    elements.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let lo = elements[0].key.clone();
+    let hi = elements[5].key.clone();
+    let digest = prover.digest().unwrap();
+    let (_, proof) = prover.generate_range_proof(&lo, &hi).unwrap();
+
+    let mut tampered = proof.to_vec();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 0xFF;
+
+    assert!(verify_range_proof(&digest, &Bytes::from(tampered), &lo, &hi, &Blake2b256Hash).is_err());
+}
+
+/// `floor((a + b) / 2)` as big-endian byte strings, both `KEY_LENGTH`
+/// bytes. For `a < b`, lands strictly between them unless they're
+/// adjacent integers — good enough for the well-spread
+/// Blake2b256-derived keys this test uses.
+fn midpoint(a: &Bytes, b: &Bytes) -> Bytes {
+    let len = a.len();
+    let mut sum = vec![0u8; len + 1];
+    let mut carry = 0u16;
+    for i in (0..len).rev() {
+        let s = a[i] as u16 + b[i] as u16 + carry;
+        sum[i + 1] = (s & 0xFF) as u8;
+        carry = s >> 8;
+    }
+    sum[0] = carry as u8;
+
+    let mut out = vec![0u8; len + 1];
+    let mut rem = 0u16;
+    for (i, byte) in sum.iter().enumerate() {
+        let cur = rem * 256 + *byte as u16;
+        out[i] = (cur / 2) as u8;
+        rem = cur % 2;
+    }
+    Bytes::from(out[1..].to_vec())
+}